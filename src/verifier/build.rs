@@ -0,0 +1,8 @@
+// Generates the gRPC service from `proto/qtop.proto`. The proto is parsed
+// with `protox` so building does not need `protoc` installed.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto");
+    let descriptors = protox::compile(["qtop.proto"], ["proto"])?;
+    tonic_prost_build::configure().compile_fds(descriptors)?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::circuit::Circuit;
+use crate::error::{QtopError, Result};
+use crate::invariant::Invariant;
+use crate::verification::VerificationResult;
+use futures::stream::{self, StreamExt};
+use std::num::NonZeroUsize;
+
+/// How [`verify_batch`] verifies its circuits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Maximum number of circuits verified at once.
+    pub concurrency: NonZeroUsize,
+    /// Allowed distance between the computed and expected value.
+    pub tolerance: u64,
+    /// Invariant each circuit is verified against.
+    pub invariant: Invariant,
+}
+
+impl Default for VerifyOptions {
+    /// One circuit per CPU, exact matches, winding numbers.
+    fn default() -> Self {
+        Self {
+            concurrency: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            tolerance: 0,
+            invariant: Invariant::Winding,
+        }
+    }
+}
+
+/// Verify `circuits` with at most `opts.concurrency` computations in flight,
+/// returning their results in input order. A circuit that cannot be
+/// verified is reported as invalid; [`try_verify_batch`] says why.
+///
+/// Each computation runs on Tokio's blocking pool, so this must be awaited
+/// within a Tokio runtime. Dropping the future stops new circuits from being
+/// started; computations already running finish in the background and their
+/// results are discarded. A panic while verifying a circuit resumes on the
+/// awaiting task.
+pub async fn verify_batch(circuits: Vec<Circuit>, opts: VerifyOptions) -> Vec<VerificationResult> {
+    stream::iter(circuits)
+        .map(|circuit| async move {
+            let unverified = VerificationResult {
+                circuit_id: circuit.id,
+                invariant: opts.invariant,
+                winding_number: 0,
+                expected_winding: circuit.expected(opts.invariant),
+                confidence: None,
+                valid: false,
+            };
+            verify_one(circuit, opts).await.unwrap_or(unverified)
+        })
+        .buffered(opts.concurrency.get())
+        .collect()
+        .await
+}
+
+/// Like [`verify_batch`], but a circuit that fails [`Circuit::validate`],
+/// has too few phases to form a loop when verifying winding numbers, or
+/// whose computation was cancelled gets that error as its result instead,
+/// without affecting the others.
+pub async fn try_verify_batch(
+    circuits: Vec<Circuit>,
+    opts: VerifyOptions,
+) -> Vec<Result<VerificationResult>> {
+    stream::iter(circuits)
+        .map(|circuit| verify_one(circuit, opts))
+        .buffered(opts.concurrency.get())
+        .collect()
+        .await
+}
+
+async fn verify_one(circuit: Circuit, opts: VerifyOptions) -> Result<VerificationResult> {
+    circuit.validate()?;
+    if opts.invariant == Invariant::Winding {
+        circuit.ensure_phase_loop()?;
+    }
+    let circuit_id = circuit.id;
+    let verification = tokio::task::spawn_blocking(move || {
+        circuit.verify_invariant(opts.invariant, opts.tolerance)
+    });
+    match verification.await {
+        Ok(result) => Ok(result),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // Cancelled, e.g. because the runtime is shutting down.
+        Err(_) => Err(QtopError::VerificationCancelled { circuit_id }),
+    }
+}
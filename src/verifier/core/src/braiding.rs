@@ -0,0 +1,35 @@
+use crate::winding::winding_number;
+use num_complex::Complex64;
+
+/// A 2×2 complex matrix in row-major order, `[a, b, c, d]` for
+/// `[[a, b], [c, d]]`.
+pub type Matrix2 = [Complex64; 4];
+
+fn determinant(m: &Matrix2) -> Complex64 {
+    m[0] * m[3] - m[1] * m[2]
+}
+
+/// Accumulated phase of a braiding sequence after each gate.
+///
+/// The phase after `k` gates is the argument of the determinant of the
+/// running product `M_k ⋯ M_1`, i.e. the sum of the phases of its
+/// eigenvalues. The sequence starts from the identity, so the first phase is
+/// always zero.
+pub fn braiding_phases(matrices: &[Matrix2]) -> Vec<f64> {
+    let mut det = Complex64::new(1.0, 0.0);
+    let mut phases = Vec::with_capacity(matrices.len() + 1);
+    phases.push(det.arg());
+    for matrix in matrices {
+        det *= determinant(matrix);
+        phases.push(det.arg());
+    }
+    phases
+}
+
+/// Net winding of the accumulated phase of a sequence of braiding matrices.
+///
+/// Each gate should advance the phase by less than π, otherwise the
+/// direction it winds in is ambiguous.
+pub fn winding_from_braiding(matrices: &[Matrix2]) -> i64 {
+    winding_number(&braiding_phases(matrices)).value
+}
@@ -0,0 +1,251 @@
+use crate::braiding::{braiding_phases, Matrix2};
+use crate::error::{QtopError, Result};
+use crate::invariant::{chern_number, Invariant, CHERN_QUANTIZATION_TOLERANCE};
+use crate::verification::VerificationResult;
+use crate::winding::{winding_number, WindingResult};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::time::Duration;
+
+/// A circuit together with its sampled phase loop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Circuit {
+    pub id: u64,
+    #[serde(default)]
+    pub phases: Vec<f64>,
+    /// Braiding matrices the circuit applies, each complex entry written
+    /// as `[re, im]`. An alternative to sampling `phases` directly.
+    #[serde(default)]
+    pub braiding: Vec<Matrix2>,
+    /// Winding the phase loop must have; when unset any non-zero winding
+    /// is accepted.
+    #[serde(default)]
+    pub expected_winding: Option<i64>,
+    /// Berry flux through each plaquette of a discretized Brillouin zone, in
+    /// radians, for verifying the circuit's Chern number.
+    #[serde(default)]
+    pub berry_flux: Vec<f64>,
+    /// Chern number the Berry flux must integrate to; when unset any
+    /// non-zero Chern number is accepted.
+    #[serde(default)]
+    pub expected_chern: Option<i64>,
+    /// Seconds between monitoring checks of this circuit, overriding the
+    /// monitor's own interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<f64>,
+}
+
+impl Circuit {
+    pub fn new(id: u64, phases: Vec<f64>) -> Self {
+        Self {
+            id,
+            phases,
+            braiding: Vec::new(),
+            expected_winding: None,
+            berry_flux: Vec::new(),
+            expected_chern: None,
+            interval: None,
+        }
+    }
+
+    pub fn with_expected_winding(mut self, expected: i64) -> Self {
+        self.expected_winding = Some(expected);
+        self
+    }
+
+    /// Keep only the phase samples in `range`, to verify the winding of
+    /// that sub-loop alone; it is closed from its last sample back to its
+    /// first like any other loop. The expected winding is dropped, as it
+    /// describes the whole loop.
+    pub fn with_phase_window(mut self, range: Range<usize>) -> Result<Self> {
+        if range.start >= range.end || range.end > self.phases.len() {
+            return Err(QtopError::PhaseRangeOutOfBounds {
+                circuit_id: self.id,
+                start: range.start,
+                end: range.end,
+                samples: self.phases.len(),
+            });
+        }
+        self.phases.truncate(range.end);
+        self.phases.drain(..range.start);
+        self.expected_winding = None;
+        Ok(self)
+    }
+
+    /// How often this circuit asks to be checked, if it overrides the
+    /// monitor's interval. An interval [`Circuit::validate`] would reject
+    /// counts as no override.
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+            .filter(|&value| value > 0.0)
+            .and_then(|value| Duration::try_from_secs_f64(value).ok())
+    }
+
+    /// Reject sample data no computation could make sense of: a NaN or
+    /// infinite phase, Berry flux or braiding matrix entry. A poll interval
+    /// must be a positive, finite number of seconds.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(value) = self.interval {
+            if !(value > 0.0 && Duration::try_from_secs_f64(value).is_ok()) {
+                return Err(QtopError::InvalidInterval {
+                    circuit_id: self.id,
+                    value,
+                });
+            }
+        }
+        let non_finite = |field, samples: &mut dyn Iterator<Item = f64>| {
+            samples
+                .enumerate()
+                .find(|(_, value)| !value.is_finite())
+                .map(|(index, value)| QtopError::NonFiniteSample {
+                    circuit_id: self.id,
+                    field,
+                    index,
+                    value,
+                })
+        };
+        let braiding = &mut self.braiding.iter().map(|matrix| {
+            if matrix.iter().all(|entry| entry.is_finite()) {
+                0.0
+            } else {
+                f64::NAN
+            }
+        });
+        let error = non_finite("phase", &mut self.phases.iter().copied())
+            .or_else(|| non_finite("berry_flux", &mut self.berry_flux.iter().copied()))
+            .or_else(|| non_finite("braiding", braiding));
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Require enough phase samples to define a winding number: a loop
+    /// through fewer than two points cannot wind, so its winding of 0 would
+    /// be meaningless rather than a violation.
+    pub fn ensure_phase_loop(&self) -> Result<()> {
+        if self.phases.len() < 2 {
+            return Err(QtopError::TooFewPhases {
+                circuit_id: self.id,
+                samples: self.phases.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Phase loop accumulated by this circuit's braiding matrices, for
+    /// verifying a circuit that was defined by braiding rather than phases.
+    pub fn braiding_phases(&self) -> Result<Vec<f64>> {
+        if self.braiding.is_empty() {
+            return Err(QtopError::InvalidPhaseData(format!(
+                "circuit {} has no braiding matrices",
+                self.id
+            )));
+        }
+        Ok(braiding_phases(&self.braiding))
+    }
+
+    /// Net winding of this circuit's phase loop.
+    pub fn winding_number(&self) -> i64 {
+        self.winding().value
+    }
+
+    /// Net winding of this circuit's phase loop, with its confidence.
+    pub fn winding(&self) -> WindingResult {
+        winding_number(&self.phases)
+    }
+
+    /// Chern number of this circuit's Berry flux, before rounding.
+    pub fn chern_number(&self) -> f64 {
+        chern_number(&self.berry_flux)
+    }
+
+    /// Value `invariant` must take for this circuit, if it has one.
+    pub fn expected(&self, invariant: Invariant) -> Option<i64> {
+        match invariant {
+            Invariant::Winding => self.expected_winding,
+            Invariant::Chern => self.expected_chern,
+        }
+    }
+
+    /// Require the loop to wind exactly `expected` times, returning the
+    /// computed winding on success.
+    pub fn ensure_winding(&self, expected: i64) -> Result<i64> {
+        self.ensure_phase_loop()?;
+        let got = self.winding_number();
+        if got != expected {
+            return Err(QtopError::WindingViolation { expected, got });
+        }
+        Ok(got)
+    }
+
+    /// Verify the circuit's winding number matches its expected winding, or
+    /// failing that, that the loop is topologically protected at all (i.e.
+    /// has a non-zero winding number).
+    pub fn verify(&self) -> VerificationResult {
+        self.verify_with_tolerance(0)
+    }
+
+    /// Like [`Circuit::verify`], but accept any winding within `tolerance`
+    /// of the expected one. The tolerance has no effect on circuits without
+    /// an expected winding.
+    pub fn verify_with_tolerance(&self, tolerance: u64) -> VerificationResult {
+        self.verify_invariant(Invariant::Winding, tolerance)
+    }
+
+    /// Verify `invariant` against its expected value within `tolerance`,
+    /// or failing that, that it is non-zero. A Chern number must in
+    /// addition be quantized: Berry flux that does not integrate to within
+    /// [`CHERN_QUANTIZATION_TOLERANCE`] of an integer is never valid.
+    pub fn verify_invariant(&self, invariant: Invariant, tolerance: u64) -> VerificationResult {
+        match invariant {
+            Invariant::Winding => self.verify_known_winding(self.winding(), tolerance),
+            Invariant::Chern => {
+                let chern = self.chern_number();
+                let rounded = chern.round();
+                let quantized = (chern - rounded).abs() <= CHERN_QUANTIZATION_TOLERANCE;
+                self.judge(Invariant::Chern, rounded as i64, None, quantized, tolerance)
+            }
+        }
+    }
+
+    /// Like [`Circuit::verify_with_tolerance`], for a winding that is already
+    /// known, e.g. from a cache of earlier computations.
+    pub fn verify_known_winding(
+        &self,
+        winding: WindingResult,
+        tolerance: u64,
+    ) -> VerificationResult {
+        let confidence = Some(winding.confidence);
+        self.judge(
+            Invariant::Winding,
+            winding.value,
+            confidence,
+            true,
+            tolerance,
+        )
+    }
+
+    fn judge(
+        &self,
+        invariant: Invariant,
+        value: i64,
+        confidence: Option<f64>,
+        quantized: bool,
+        tolerance: u64,
+    ) -> VerificationResult {
+        let expected = self.expected(invariant);
+        let matches = match expected {
+            Some(expected) => value.abs_diff(expected) <= tolerance,
+            None => value != 0,
+        };
+        VerificationResult {
+            circuit_id: self.id,
+            invariant,
+            winding_number: value,
+            expected_winding: expected,
+            confidence,
+            valid: quantized && matches,
+        }
+    }
+}
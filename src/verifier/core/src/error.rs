@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors produced by the core library.
+#[derive(Debug, Error)]
+pub enum QtopError {
+    #[error("circuit {0} not found")]
+    CircuitNotFound(u64),
+
+    #[error("invalid phase data: {0}")]
+    InvalidPhaseData(String),
+
+    #[error("circuit {circuit_id}: {field} sample {index} is not a finite number ({value})")]
+    NonFiniteSample {
+        circuit_id: u64,
+        field: &'static str,
+        index: usize,
+        value: f64,
+    },
+
+    #[error(
+        "circuit {circuit_id} has {samples} phase sample(s); a winding number needs at least 2"
+    )]
+    TooFewPhases { circuit_id: u64, samples: usize },
+
+    #[error(
+        "circuit {circuit_id}: phase range {start}..{end} is out of bounds for {samples} sample(s)"
+    )]
+    PhaseRangeOutOfBounds {
+        circuit_id: u64,
+        start: usize,
+        end: usize,
+        samples: usize,
+    },
+
+    #[error("circuit {circuit_id}: interval must be a positive number of seconds, got {value}")]
+    InvalidInterval { circuit_id: u64, value: f64 },
+
+    #[error("verification of circuit {circuit_id} was cancelled")]
+    VerificationCancelled { circuit_id: u64 },
+
+    #[error("winding number violation: expected {expected}, got {got}")]
+    WindingViolation { expected: i64, got: i64 },
+
+    #[error("unsupported circuit file {}: expected a .json or .toml extension, optionally followed by .gz", .0.display())]
+    UnsupportedFormat(PathBuf),
+
+    #[error("malformed circuit file: {0}")]
+    Parse(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, QtopError>;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the invariant computations. Bump it whenever a change could
+/// alter the value computed for some input, so that recorded results can
+/// tell which algorithm produced them.
+pub const ALGORITHM_VERSION: u32 = 1;
+
+/// How far a Chern number computed from sampled Berry flux may sit from
+/// the nearest integer and still count as quantized.
+pub const CHERN_QUANTIZATION_TOLERANCE: f64 = 0.05;
+
+/// Topological invariant a circuit is verified against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Invariant {
+    /// Winding number of the circuit's phase loop.
+    #[default]
+    Winding,
+    /// Chern number of the circuit's Berry flux over the Brillouin zone.
+    Chern,
+}
+
+impl Invariant {
+    pub fn name(self) -> &'static str {
+        match self {
+            Invariant::Winding => "winding",
+            Invariant::Chern => "chern",
+        }
+    }
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Invariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "winding" => Ok(Invariant::Winding),
+            "chern" => Ok(Invariant::Chern),
+            other => Err(format!(
+                "unknown invariant `{}`: expected `winding` or `chern`",
+                other
+            )),
+        }
+    }
+}
+
+/// Chern number of a discretized Brillouin zone: the total Berry flux
+/// through its plaquettes divided by 2π. Exact sampling gives an integer;
+/// the fractional part measures how far the sampling is from that.
+pub fn chern_number(berry_flux: &[f64]) -> f64 {
+    berry_flux.iter().sum::<f64>() / TAU
+}
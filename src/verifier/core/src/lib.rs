@@ -0,0 +1,22 @@
+//! Core topological invariant computations for the quantum topology proxy.
+//!
+//! The verifier binary is a thin shell around this crate; anything that
+//! needs to reason about circuit phase data lives here.
+
+pub mod batch;
+pub mod braiding;
+pub mod circuit;
+pub mod error;
+pub mod invariant;
+pub mod loader;
+pub mod verification;
+pub mod winding;
+
+pub use batch::{try_verify_batch, verify_batch, VerifyOptions};
+pub use braiding::{braiding_phases, winding_from_braiding, Matrix2};
+pub use circuit::Circuit;
+pub use error::{QtopError, Result};
+pub use invariant::{chern_number, Invariant, ALGORITHM_VERSION};
+pub use loader::{find_circuit, find_circuit_files, load_circuits, parse_phases};
+pub use verification::VerificationResult;
+pub use winding::{winding_number, WindingResult};
@@ -0,0 +1,294 @@
+use crate::circuit::Circuit;
+use crate::error::{QtopError, Result};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// On-disk circuit definitions. Both JSON and TOML files carry a top-level
+/// `circuits` list, so the same structure reads naturally as a TOML array of
+/// tables (`[[circuits]]`).
+#[derive(Deserialize)]
+struct CircuitFile {
+    circuits: Vec<Circuit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Json,
+    Toml,
+}
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl FileKind {
+    /// Kind of a circuit file from its extension, looking through a trailing
+    /// `.gz`: `circuits.json.gz` is a JSON file.
+    fn from_path(path: &Path) -> Result<Self> {
+        let inner = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Path::new(path.file_stem().unwrap_or_default()),
+            _ => path,
+        };
+        match inner.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(FileKind::Json),
+            Some("toml") => Ok(FileKind::Toml),
+            _ => Err(QtopError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+}
+
+/// Load circuit definitions from a `.json` or `.toml` file, which may be
+/// gzip-compressed (`.json.gz`, `.toml.gz`). Every circuit is validated
+/// with [`Circuit::validate`] before it is returned.
+pub fn load_circuits(path: &Path) -> Result<Vec<Circuit>> {
+    let kind = FileKind::from_path(path)?;
+    let contents = decode(std::fs::read(path)?)?;
+    parse(&contents, kind)
+}
+
+/// File contents as text, decompressing them first if they are gzip. This
+/// goes by the content rather than the name, so a compressed file that lost
+/// its `.gz` still loads.
+fn decode(bytes: Vec<u8>) -> Result<String> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut contents = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+    }
+}
+
+fn parse(contents: &str, kind: FileKind) -> Result<Vec<Circuit>> {
+    let file: CircuitFile = match kind {
+        FileKind::Json => {
+            serde_json::from_str(contents).map_err(|e| QtopError::Parse(e.to_string()))?
+        }
+        FileKind::Toml => toml::from_str(contents).map_err(|e| QtopError::Parse(e.to_string()))?,
+    };
+    for circuit in &file.circuits {
+        circuit.validate()?;
+    }
+    Ok(file.circuits)
+}
+
+/// Find every `.json` and `.toml` file (compressed or not) under `dir`,
+/// recursively, in
+/// sorted order. Symlinked directories are followed, but each directory is
+/// visited only once so symlink loops cannot recurse forever.
+pub fn find_circuit_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if !visited.insert(dir.canonicalize()?) {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            // `is_dir` and `is_file` follow symlinks.
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() && FileKind::from_path(&path).is_ok() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parse phase samples separated by commas and/or whitespace, e.g.
+/// `0, 1.57, 3.14` or one value per line.
+pub fn parse_phases(input: &str) -> Result<Vec<f64>> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| QtopError::InvalidPhaseData(format!("'{}' is not a number", token)))
+        })
+        .collect()
+}
+
+pub fn find_circuit(circuits: &[Circuit], id: u64) -> Result<&Circuit> {
+    circuits
+        .iter()
+        .find(|circuit| circuit.id == id)
+        .ok_or(QtopError::CircuitNotFound(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_json_and_toml_alike() {
+        let json = r#"{"circuits": [
+            {"id": 42, "phases": [0.0, 1.57, 3.14, 4.71], "expected_winding": 1},
+            {"id": 7, "phases": [0.0, 0.0]}
+        ]}"#;
+        let toml = r#"
+            [[circuits]]
+            id = 42
+            phases = [0.0, 1.57, 3.14, 4.71]
+            expected_winding = 1
+
+            [[circuits]]
+            id = 7
+            phases = [0.0, 0.0]
+        "#;
+
+        let from_json = parse(json, FileKind::Json).unwrap();
+        assert_eq!(from_json, parse(toml, FileKind::Toml).unwrap());
+        assert_eq!(from_json[0].expected_winding, Some(1));
+        assert_eq!(from_json[1].expected_winding, None);
+    }
+
+    #[test]
+    fn braiding_matrices_parse_as_re_im_pairs() {
+        let toml = r#"
+            [[circuits]]
+            id = 5
+            braiding = [[[0.5, 0.5], [0.5, -0.5], [0.5, -0.5], [0.5, 0.5]]]
+        "#;
+
+        let circuits = parse(toml, FileKind::Toml).unwrap();
+        assert!(circuits[0].phases.is_empty());
+        assert_eq!(circuits[0].braiding.len(), 1);
+        assert_eq!(circuits[0].braiding[0][1].im, -0.5);
+    }
+
+    #[test]
+    fn circuit_files_are_found_recursively() {
+        let root = std::env::temp_dir().join(format!("qtop-loader-{}", std::process::id()));
+        let nested = root.join("experiment-a").join("run-1");
+        std::fs::create_dir_all(&nested).unwrap();
+        for file in [
+            "top.toml",
+            "notes.txt",
+            "archive.txt.gz",
+            "experiment-a/run-1/deep.json",
+            "experiment-a/run-1/packed.json.gz",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, nested.join("loop")).unwrap();
+
+        let files = find_circuit_files(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|path| path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("experiment-a/run-1/deep.json"),
+                PathBuf::from("experiment-a/run-1/packed.json.gz"),
+                PathBuf::from("top.toml")
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_circuit_is_an_error() {
+        let circuits = vec![Circuit::new(1, vec![0.0])];
+        assert!(matches!(
+            find_circuit(&circuits, 42),
+            Err(QtopError::CircuitNotFound(42))
+        ));
+    }
+
+    #[test]
+    fn phases_parse_from_commas_and_newlines() {
+        assert_eq!(
+            parse_phases("0,1.5, 3\n4.5\n").unwrap(),
+            vec![0.0, 1.5, 3.0, 4.5]
+        );
+        assert!(parse_phases("  \n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_phase_names_the_token() {
+        let err = parse_phases("0, 1.57, pi, 4.71").unwrap_err();
+        assert_eq!(err.to_string(), "invalid phase data: 'pi' is not a number");
+    }
+
+    #[test]
+    fn non_finite_phase_fails_the_load() {
+        let toml = r#"
+            [[circuits]]
+            id = 3
+            phases = [0.0, 1.57, nan, 4.71]
+        "#;
+        assert!(matches!(
+            parse(toml, FileKind::Toml),
+            Err(QtopError::NonFiniteSample {
+                circuit_id: 3,
+                index: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn poll_interval_must_be_positive() {
+        let with_interval = |interval: &str| {
+            let toml = format!(
+                "[[circuits]]\nid = 4\nphases = [0.0, 3.14]\ninterval = {}",
+                interval
+            );
+            parse(&toml, FileKind::Toml)
+        };
+        let circuits = with_interval("2.5").unwrap();
+        assert_eq!(circuits[0].interval(), Some(Duration::from_millis(2500)));
+        assert!(matches!(
+            with_interval("0"),
+            Err(QtopError::InvalidInterval { circuit_id: 4, .. })
+        ));
+        assert!(with_interval("-1").is_err());
+
+        // Unvalidated circuits fall back to the monitor's interval.
+        let mut circuit = Circuit::new(4, vec![0.0, 1.5]);
+        for value in [-1.0, 0.0, f64::NAN, f64::INFINITY] {
+            circuit.interval = Some(value);
+            assert_eq!(circuit.interval(), None);
+        }
+    }
+
+    #[test]
+    fn unknown_extension_is_rejected() {
+        for path in ["circuits.yaml", "circuits.gz", "circuits.yaml.gz"] {
+            assert!(matches!(
+                load_circuits(Path::new(path)),
+                Err(QtopError::UnsupportedFormat(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn gzip_contents_are_decompressed() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let toml = "[[circuits]]\nid = 9\nphases = [0.0, 3.14]\n";
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(toml.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decode(compressed).unwrap(), toml);
+        assert_eq!(decode(toml.as_bytes().to_vec()).unwrap(), toml);
+        assert!(decode(GZIP_MAGIC.to_vec()).is_err());
+        assert_eq!(
+            FileKind::from_path(Path::new("runs/a.toml.gz")).unwrap(),
+            FileKind::Toml
+        );
+    }
+}
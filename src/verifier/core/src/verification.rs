@@ -0,0 +1,22 @@
+use crate::invariant::Invariant;
+use serde::{Deserialize, Serialize};
+
+/// Outcome of verifying a single circuit's topological invariant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub circuit_id: u64,
+    /// Which invariant was measured.
+    pub invariant: Invariant,
+    /// Measured value of the invariant. The name predates support for
+    /// invariants other than the winding number.
+    pub winding_number: i64,
+    /// Value the invariant was checked against, if it had one.
+    pub expected_winding: Option<i64>,
+    /// How far to trust a winding number, as in [`WindingResult`]. Absent
+    /// for Chern numbers and for results from verifiers predating it.
+    ///
+    /// [`WindingResult`]: crate::WindingResult
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    pub valid: bool,
+}
@@ -0,0 +1,54 @@
+use serde::Serialize;
+use std::f64::consts::{PI, TAU};
+
+/// A winding number and how far to trust it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WindingResult {
+    pub value: i64,
+    /// `1 - largest step / π`, from 1 for a finely sampled loop down to 0
+    /// when some step sat right at the π aliasing threshold. A step near π
+    /// could as well have gone the other way round, which would change the
+    /// winding by one.
+    pub confidence: f64,
+}
+
+/// Wrap a phase difference into the principal interval `(-π, π]`.
+fn wrap_phase(delta: f64) -> f64 {
+    let wrapped = (delta + PI).rem_euclid(TAU) - PI;
+    if wrapped == -PI {
+        PI
+    } else {
+        wrapped
+    }
+}
+
+/// Net winding number of a closed phase loop.
+///
+/// The phases are treated as samples around a loop: each step between
+/// consecutive samples, including the closing step from the last sample back
+/// to the first, is wrapped into `(-π, π]` and summed. The total is an
+/// integer multiple of 2π for any closed loop, so the result is rounded to
+/// the nearest integer. The confidence comes from the largest wrapped step.
+pub fn winding_number(phases: &[f64]) -> WindingResult {
+    if phases.is_empty() {
+        return WindingResult {
+            value: 0,
+            confidence: 1.0,
+        };
+    }
+
+    let closing = (phases[phases.len() - 1], phases[0]);
+    let (total, largest) = phases
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .chain(std::iter::once(closing))
+        .map(|(from, to)| wrap_phase(to - from))
+        .fold((0.0, 0.0_f64), |(total, largest), step| {
+            (total + step, largest.max(step.abs()))
+        });
+
+    WindingResult {
+        value: (total / TAU).round() as i64,
+        confidence: 1.0 - largest / PI,
+    }
+}
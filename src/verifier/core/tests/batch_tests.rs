@@ -0,0 +1,92 @@
+use qtop_core::{try_verify_batch, verify_batch, Circuit, Invariant, QtopError, VerifyOptions};
+use std::f64::consts::TAU;
+use std::num::NonZeroUsize;
+
+fn loop_circuit(id: u64, samples: usize, turns: f64) -> Circuit {
+    let phases = (0..samples)
+        .map(|i| turns * TAU * i as f64 / samples as f64)
+        .collect();
+    Circuit::new(id, phases).with_expected_winding(1)
+}
+
+#[tokio::test]
+async fn results_come_back_in_input_order() {
+    // The largest circuit goes first so it is likely to finish last.
+    let circuits = vec![
+        loop_circuit(3, 100_000, 1.0),
+        loop_circuit(1, 8, 2.0),
+        loop_circuit(2, 8, 1.0),
+    ];
+    let opts = VerifyOptions {
+        concurrency: NonZeroUsize::new(3).unwrap(),
+        ..VerifyOptions::default()
+    };
+
+    let results = verify_batch(circuits, opts).await;
+    let ids: Vec<_> = results.iter().map(|result| result.circuit_id).collect();
+    assert_eq!(ids, [3, 1, 2]);
+    let valid: Vec<_> = results.iter().map(|result| result.valid).collect();
+    assert_eq!(valid, [true, false, true]);
+}
+
+#[tokio::test]
+async fn options_select_tolerance_and_invariant() {
+    let circuits = vec![loop_circuit(1, 8, 2.0)];
+    let lenient = VerifyOptions {
+        tolerance: 1,
+        ..VerifyOptions::default()
+    };
+    assert!(verify_batch(circuits.clone(), lenient).await[0].valid);
+
+    let chern = VerifyOptions {
+        invariant: Invariant::Chern,
+        ..VerifyOptions::default()
+    };
+    let results = verify_batch(circuits, chern).await;
+    assert_eq!(results[0].invariant, Invariant::Chern);
+}
+
+#[tokio::test]
+async fn chern_circuits_need_no_phase_loop() {
+    let mut circuit = Circuit::new(1, vec![]);
+    circuit.berry_flux = vec![TAU / 4.0; 4];
+    circuit.expected_chern = Some(1);
+    let chern = VerifyOptions {
+        invariant: Invariant::Chern,
+        ..VerifyOptions::default()
+    };
+
+    let results = verify_batch(vec![circuit], chern).await;
+    assert_eq!(results[0].winding_number, 1);
+    assert!(results[0].valid);
+}
+
+#[tokio::test]
+async fn invalid_circuits_fail_alone() {
+    let circuits = vec![
+        Circuit::new(1, vec![0.0, f64::NAN, 1.0]),
+        Circuit::new(2, vec![0.5]),
+        loop_circuit(3, 8, 1.0),
+    ];
+
+    let valid: Vec<_> = verify_batch(circuits.clone(), VerifyOptions::default())
+        .await
+        .iter()
+        .map(|result| result.valid)
+        .collect();
+    assert_eq!(valid, [false, false, true]);
+
+    let results = try_verify_batch(circuits, VerifyOptions::default()).await;
+    assert!(matches!(
+        results[0],
+        Err(QtopError::NonFiniteSample { circuit_id: 1, .. })
+    ));
+    assert!(matches!(
+        results[1],
+        Err(QtopError::TooFewPhases {
+            circuit_id: 2,
+            samples: 1
+        })
+    ));
+    assert!(results[2].as_ref().unwrap().valid);
+}
@@ -0,0 +1,51 @@
+use num_complex::Complex64;
+use qtop_core::{winding_from_braiding, Circuit, Matrix2};
+
+/// √SWAP on a single pair of modes: four of them compose to a full swap and
+/// back, while the determinant picks up a phase of i per gate.
+fn sqrt_swap() -> Matrix2 {
+    let p = Complex64::new(0.5, 0.5);
+    let m = Complex64::new(0.5, -0.5);
+    [p, m, m, p]
+}
+
+fn adjoint(matrix: Matrix2) -> Matrix2 {
+    let [a, b, c, d] = matrix;
+    [a.conj(), c.conj(), b.conj(), d.conj()]
+}
+
+#[test]
+fn no_braiding_has_zero_winding() {
+    assert_eq!(winding_from_braiding(&[]), 0);
+}
+
+#[test]
+fn four_sqrt_swaps_wind_once() {
+    assert_eq!(winding_from_braiding(&[sqrt_swap(); 4]), 1);
+}
+
+#[test]
+fn inverse_swaps_wind_backwards() {
+    assert_eq!(winding_from_braiding(&[adjoint(sqrt_swap()); 4]), -1);
+}
+
+#[test]
+fn swap_and_inverse_cancel() {
+    let sequence = [
+        sqrt_swap(),
+        adjoint(sqrt_swap()),
+        sqrt_swap(),
+        adjoint(sqrt_swap()),
+    ];
+    assert_eq!(winding_from_braiding(&sequence), 0);
+}
+
+#[test]
+fn circuit_braiding_phases_match_winding() {
+    let mut circuit = Circuit::new(3, Vec::new());
+    assert!(circuit.braiding_phases().is_err());
+
+    circuit.braiding = vec![sqrt_swap(); 8];
+    circuit.phases = circuit.braiding_phases().unwrap();
+    assert_eq!(circuit.winding_number(), 2);
+}
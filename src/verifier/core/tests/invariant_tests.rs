@@ -0,0 +1,53 @@
+use qtop_core::{chern_number, Circuit, Invariant};
+use std::f64::consts::TAU;
+
+fn chern_circuit(id: u64, berry_flux: Vec<f64>) -> Circuit {
+    let mut circuit = Circuit::new(id, Vec::new());
+    circuit.berry_flux = berry_flux;
+    circuit
+}
+
+#[test]
+fn flux_of_one_flux_quantum_has_unit_chern_number() {
+    assert!((chern_number(&[TAU / 4.0; 4]) - 1.0).abs() < 1e-12);
+    assert_eq!(chern_number(&[]), 0.0);
+}
+
+#[test]
+fn winding_remains_the_default_invariant() {
+    let circuit = Circuit::new(1, vec![0.0, TAU / 3.0, 2.0 * TAU / 3.0]).with_expected_winding(1);
+    let result = circuit.verify();
+    assert_eq!(result.invariant, Invariant::Winding);
+    assert_eq!(result, circuit.verify_invariant(Invariant::default(), 0));
+    assert!(result.valid);
+}
+
+#[test]
+fn chern_is_checked_against_its_own_expectation() {
+    let mut circuit = chern_circuit(2, vec![-TAU / 2.0; 4]).with_expected_winding(5);
+    circuit.expected_chern = Some(-2);
+
+    let result = circuit.verify_invariant(Invariant::Chern, 0);
+    assert_eq!(result.invariant, Invariant::Chern);
+    assert_eq!(result.winding_number, -2);
+    assert_eq!(result.expected_winding, Some(-2));
+    assert!(result.valid);
+}
+
+#[test]
+fn fractional_chern_number_is_never_valid() {
+    // Sums to half a flux quantum; rounding alone would call it valid.
+    let circuit = chern_circuit(3, vec![TAU / 8.0; 4]);
+    assert!(!circuit.verify_invariant(Invariant::Chern, 1).valid);
+
+    let nearly_quantized = chern_circuit(4, vec![TAU / 4.0 + 0.01; 4]);
+    assert!(nearly_quantized.verify_invariant(Invariant::Chern, 0).valid);
+}
+
+#[test]
+fn invariant_names_round_trip() {
+    for invariant in [Invariant::Winding, Invariant::Chern] {
+        assert_eq!(invariant.to_string().parse::<Invariant>(), Ok(invariant));
+    }
+    assert!("berry".parse::<Invariant>().is_err());
+}
@@ -0,0 +1,163 @@
+use qtop_core::{winding_number, Circuit, QtopError};
+use std::f64::consts::TAU;
+
+fn loop_phases(samples: usize, turns: f64) -> Vec<f64> {
+    (0..samples)
+        .map(|i| turns * TAU * i as f64 / samples as f64)
+        .collect()
+}
+
+#[test]
+fn constant_phase_has_zero_winding() {
+    assert_eq!(winding_number(&[1.25; 16]).value, 0);
+}
+
+#[test]
+fn full_positive_loop_winds_once() {
+    assert_eq!(winding_number(&loop_phases(8, 1.0)).value, 1);
+}
+
+#[test]
+fn full_negative_loop_winds_backwards() {
+    assert_eq!(winding_number(&loop_phases(8, -1.0)).value, -1);
+}
+
+#[test]
+fn closing_step_wraps_from_last_to_first_sample() {
+    // Only the closing step crosses the branch cut: 3π/2 back to 0 is a
+    // forward step of π/2, not a backward step of 3π/2.
+    let phases = [0.0, TAU / 4.0, TAU / 2.0, 3.0 * TAU / 4.0];
+    assert_eq!(winding_number(&phases).value, 1);
+}
+
+#[test]
+fn unwrapped_samples_are_reduced_modulo_two_pi() {
+    let phases: Vec<f64> = loop_phases(12, 1.0).iter().map(|p| p + 5.0 * TAU).collect();
+    assert_eq!(winding_number(&phases).value, 1);
+}
+
+#[test]
+fn confidence_falls_as_steps_near_pi() {
+    // Eight samples per turn step π/4 at a time, a quarter of the way to
+    // the aliasing threshold.
+    let fine = winding_number(&loop_phases(8, 1.0));
+    assert!((fine.confidence - 0.75).abs() < 1e-9);
+    assert_eq!(winding_number(&[1.25; 16]).confidence, 1.0);
+
+    // Three samples per turn step 2π/3, leaving little margin.
+    let coarse = winding_number(&loop_phases(3, 1.0));
+    assert_eq!(coarse.value, 1);
+    assert!((coarse.confidence - 1.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn phase_window_winds_on_its_own() {
+    // One turn forward over the first 16 samples, then one back.
+    let mut phases = loop_phases(16, 1.0);
+    phases.extend(loop_phases(16, -1.0));
+    let circuit = Circuit::new(8, phases).with_expected_winding(0);
+    assert_eq!(circuit.winding_number(), 0);
+
+    let forward = circuit.clone().with_phase_window(0..16).unwrap();
+    assert_eq!(forward.phases.len(), 16);
+    assert_eq!(forward.winding_number(), 1);
+    assert_eq!(forward.expected_winding, None);
+    let backward = circuit.clone().with_phase_window(16..32).unwrap();
+    assert_eq!(backward.winding_number(), -1);
+
+    for range in [0..33, 5..5] {
+        assert!(matches!(
+            circuit.clone().with_phase_window(range),
+            Err(QtopError::PhaseRangeOutOfBounds {
+                circuit_id: 8,
+                samples: 32,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn circuit_delegates_to_winding_number() {
+    let circuit = Circuit::new(7, loop_phases(10, 2.0));
+    assert_eq!(circuit.winding_number(), 2);
+}
+
+#[test]
+fn trivial_loop_fails_verification() {
+    let result = Circuit::new(3, vec![0.5; 4]).verify();
+    assert_eq!(result.winding_number, 0);
+    assert!(!result.valid);
+}
+
+#[test]
+fn expected_winding_must_match_exactly() {
+    let circuit = Circuit::new(4, loop_phases(8, 1.0));
+    assert!(circuit.clone().with_expected_winding(1).verify().valid);
+    assert!(!circuit.with_expected_winding(2).verify().valid);
+    assert!(
+        Circuit::new(5, vec![0.0; 4])
+            .with_expected_winding(0)
+            .verify()
+            .valid
+    );
+}
+
+#[test]
+fn ensure_winding_reports_the_mismatch() {
+    let circuit = Circuit::new(6, loop_phases(8, -1.0));
+    assert_eq!(circuit.ensure_winding(-1).unwrap(), -1);
+    assert!(matches!(
+        circuit.ensure_winding(1),
+        Err(QtopError::WindingViolation {
+            expected: 1,
+            got: -1
+        })
+    ));
+}
+
+#[test]
+fn tolerance_widens_the_accepted_range() {
+    let circuit = Circuit::new(8, loop_phases(16, 2.0)).with_expected_winding(1);
+    assert!(!circuit.verify().valid);
+    assert!(circuit.verify_with_tolerance(1).valid);
+
+    let result = circuit.verify_with_tolerance(1);
+    assert_eq!(result.expected_winding, Some(1));
+    assert_eq!(result.winding_number, 2);
+}
+
+#[test]
+fn non_finite_sample_is_reported_by_index() {
+    let circuit = Circuit::new(9, vec![0.0, 1.0, f64::NAN, 3.0]);
+    let err = circuit.validate().unwrap_err();
+    assert!(matches!(
+        err,
+        QtopError::NonFiniteSample {
+            circuit_id: 9,
+            field: "phase",
+            index: 2,
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "circuit 9: phase sample 2 is not a finite number (NaN)"
+    );
+    assert!(Circuit::new(9, vec![0.0, f64::INFINITY])
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn fewer_than_two_samples_define_no_winding() {
+    for phases in [vec![], vec![1.0]] {
+        let samples = phases.len();
+        let circuit = Circuit::new(4, phases);
+        assert!(circuit.validate().is_ok());
+        assert!(matches!(
+            circuit.ensure_winding(0),
+            Err(QtopError::TooFewPhases { circuit_id: 4, samples: n }) if n == samples
+        ));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::retry::RetryPolicy;
+use chrono::{DateTime, Utc};
+use qtop_core::VerificationResult;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often suppressed alerts are summarized, and the period `--alert-rate`
+/// counts over.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Body POSTed to the alert webhook for each new violation.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertPayload {
+    pub circuit_id: u64,
+    pub winding_number: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Body POSTed in place of the alerts the rate limit held back.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuppressedPayload {
+    pub suppressed: usize,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Sends webhook alerts for winding violations found while monitoring.
+///
+/// A circuit alerts once when it becomes invalid and stays quiet while it
+/// remains broken; it re-arms after a valid verification. At most
+/// `per_minute` alerts go out each minute, so a systemic failure can't flood
+/// the endpoint; the rest are summarized once a minute by [`summarize`],
+/// and whatever is still pending when the alerter is dropped or
+/// [`close`]d.
+///
+/// [`summarize`]: Alerter::summarize
+/// [`close`]: Alerter::close
+pub struct Alerter {
+    client: reqwest::Client,
+    url: Url,
+    retry: RetryPolicy,
+    firing: HashSet<u64>,
+    limit: RateLimit,
+}
+
+impl Alerter {
+    pub fn new(url: Url, retry: RetryPolicy, per_minute: u32) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            url,
+            retry,
+            firing: HashSet::new(),
+            limit: RateLimit::new(per_minute, Instant::now()),
+        }
+    }
+
+    /// Record a verification result, alerting in the background if it
+    /// starts a new violation. Delivery failures are logged and otherwise
+    /// ignored so they can never take the monitor down.
+    pub fn observe(&mut self, result: &VerificationResult) {
+        if !self.should_alert(result) {
+            return;
+        }
+        if !self.limit.admit(Instant::now()) {
+            debug!(
+                circuit_id = result.circuit_id,
+                "violation alert suppressed by --alert-rate"
+            );
+            return;
+        }
+
+        let payload = AlertPayload {
+            circuit_id: result.circuit_id,
+            winding_number: result.winding_number,
+            timestamp: Utc::now(),
+        };
+        let delivery = self.deliver(payload.clone());
+        tokio::spawn(async move {
+            match delivery.await {
+                Ok(()) => info!(circuit_id = payload.circuit_id, "violation alert delivered"),
+                Err(e) => warn!(
+                    circuit_id = payload.circuit_id,
+                    error = format!("{:#}", e),
+                    "failed to deliver violation alert"
+                ),
+            }
+        });
+    }
+
+    /// Send the summary of suppressed alerts, in the background, once a
+    /// minute has passed since the first of them. Called periodically
+    /// rather than from [`observe`](Alerter::observe), so the summary goes
+    /// out even when no further results arrive.
+    pub fn summarize(&mut self) {
+        if let Some(suppressed) = self.limit.summary_due(Instant::now()) {
+            tokio::spawn(self.summary(suppressed, "in the last minute"));
+        }
+    }
+
+    /// Summarize any alerts still suppressed and wait for the summary to be
+    /// delivered, so stopping doesn't lose them.
+    pub async fn close(mut self) {
+        if let Some(suppressed) = self.limit.flush() {
+            self.summary(suppressed, "before alerting stopped").await;
+        }
+    }
+
+    fn summary(
+        &self,
+        suppressed: usize,
+        when: &str,
+    ) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let payload = SuppressedPayload {
+            suppressed,
+            message: format!("{} violations suppressed {}", suppressed, when),
+            timestamp: Utc::now(),
+        };
+        let delivery = self.deliver(payload);
+        async move {
+            match delivery.await {
+                Ok(()) => info!(suppressed, "suppressed-alert summary delivered"),
+                Err(e) => warn!(
+                    suppressed,
+                    error = format!("{:#}", e),
+                    "failed to deliver suppressed-alert summary"
+                ),
+            }
+        }
+    }
+
+    /// POST `payload` to the webhook, retrying per the policy.
+    fn deliver(
+        &self,
+        payload: impl Serialize + Send + Sync + 'static,
+    ) -> impl std::future::Future<Output = anyhow::Result<()>> + Send + 'static {
+        let (client, url, retry) = (self.client.clone(), self.url.clone(), self.retry);
+        async move {
+            retry
+                .send(|| client.post(url.clone()).json(&payload))
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+    }
+
+    fn should_alert(&mut self, result: &VerificationResult) -> bool {
+        if result.valid {
+            self.firing.remove(&result.circuit_id);
+            false
+        } else {
+            self.firing.insert(result.circuit_id)
+        }
+    }
+}
+
+/// An alerter replaced on reload is dropped rather than
+/// [`close`](Alerter::close)d, so its pending summary goes out in the
+/// background.
+impl Drop for Alerter {
+    fn drop(&mut self) {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            if let Some(suppressed) = self.limit.flush() {
+                runtime.spawn(self.summary(suppressed, "before alerting stopped"));
+            }
+        }
+    }
+}
+
+/// Token bucket holding up to a minute's worth of alerts and refilling
+/// continuously, so a burst may use the whole allowance at once.
+struct RateLimit {
+    per_minute: f64,
+    tokens: f64,
+    refilled: Instant,
+    suppressed: usize,
+    /// When the first alert since the last summary was suppressed.
+    suppressing_since: Option<Instant>,
+}
+
+impl RateLimit {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            per_minute: f64::from(per_minute),
+            tokens: f64::from(per_minute),
+            refilled: now,
+            suppressed: 0,
+            suppressing_since: None,
+        }
+    }
+
+    /// Take a token for an alert, or count it as suppressed if none is left.
+    fn admit(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled);
+        self.tokens = (self.tokens
+            + self.per_minute * elapsed.as_secs_f64() / RATE_WINDOW.as_secs_f64())
+        .min(self.per_minute);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            self.suppressed += 1;
+            self.suppressing_since.get_or_insert(now);
+            false
+        }
+    }
+
+    /// How many alerts were suppressed, once a window has passed since the
+    /// first of them; resets the count.
+    fn summary_due(&mut self, now: Instant) -> Option<usize> {
+        let since = self.suppressing_since?;
+        if now.saturating_duration_since(since) < RATE_WINDOW {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// How many alerts were suppressed since the last summary, if any,
+    /// however recently; resets the count.
+    fn flush(&mut self) -> Option<usize> {
+        self.suppressing_since = None;
+        Some(std::mem::take(&mut self.suppressed)).filter(|&suppressed| suppressed > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qtop_core::Invariant;
+
+    fn result(circuit_id: u64, valid: bool) -> VerificationResult {
+        VerificationResult {
+            circuit_id,
+            invariant: Invariant::Winding,
+            winding_number: i64::from(valid),
+            expected_winding: None,
+            confidence: None,
+            valid,
+        }
+    }
+
+    #[test]
+    fn alerts_once_per_violation_until_recovered() {
+        let mut alerter = Alerter::new(
+            "http://127.0.0.1:1/alerts".parse().unwrap(),
+            RetryPolicy {
+                max_retries: 0,
+                base_delay: Duration::ZERO,
+            },
+            10,
+        );
+
+        assert!(!alerter.should_alert(&result(1, true)));
+        assert!(alerter.should_alert(&result(1, false)));
+        assert!(!alerter.should_alert(&result(1, false)));
+        assert!(alerter.should_alert(&result(2, false)));
+
+        assert!(!alerter.should_alert(&result(1, true)));
+        assert!(alerter.should_alert(&result(1, false)));
+    }
+
+    #[tokio::test]
+    async fn closing_sends_the_pending_summary() {
+        let (received, mut deliveries) = tokio::sync::mpsc::unbounded_channel();
+        let webhook = axum::Router::new().route(
+            "/alerts",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                let _ = received.send(body);
+                async {}
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, webhook).await });
+
+        let retry = RetryPolicy {
+            max_retries: 0,
+            base_delay: Duration::ZERO,
+        };
+        let mut alerter = Alerter::new(url.parse().unwrap(), retry, 1);
+        alerter.observe(&result(1, false));
+        alerter.observe(&result(2, false));
+        alerter.observe(&result(3, false));
+        // Well within the minute, so nothing is due yet.
+        alerter.summarize();
+        alerter.close().await;
+
+        let mut bodies = Vec::new();
+        while let Ok(Some(body)) =
+            tokio::time::timeout(Duration::from_millis(200), deliveries.recv()).await
+        {
+            bodies.push(body);
+        }
+        assert_eq!(bodies.len(), 2);
+        assert!(bodies.iter().any(|body| body["circuit_id"] == 1));
+        assert!(bodies.iter().any(|body| body["suppressed"] == 2));
+    }
+
+    #[test]
+    fn alerts_beyond_the_rate_are_summarized() {
+        let start = Instant::now();
+        let mut limit = RateLimit::new(2, start);
+
+        assert!(limit.admit(start));
+        assert!(limit.admit(start));
+        assert!(!limit.admit(start));
+        assert!(!limit.admit(start + Duration::from_secs(10)));
+        // Half a minute refills one of the two tokens.
+        assert!(limit.admit(start + Duration::from_secs(30)));
+        assert!(!limit.admit(start + Duration::from_secs(30)));
+
+        assert_eq!(limit.summary_due(start + Duration::from_secs(59)), None);
+        assert_eq!(limit.summary_due(start + RATE_WINDOW), Some(3));
+        assert_eq!(limit.summary_due(start + 2 * RATE_WINDOW), None);
+
+        // Stopping sends what's pending without waiting out the window.
+        let later = start + 2 * RATE_WINDOW;
+        assert!(limit.admit(later) && limit.admit(later));
+        assert!(!limit.admit(later));
+        assert_eq!(limit.summary_due(later), None);
+        assert_eq!(limit.summary_due(start + 2 * RATE_WINDOW), None);
+        assert_eq!(limit.flush(), Some(1));
+        assert_eq!(limit.flush(), None);
+    }
+}
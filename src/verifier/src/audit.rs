@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One emergency shutdown attempt, written as a line of JSON.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownRecord {
+    pub timestamp: DateTime<Utc>,
+    pub circuit_id: u64,
+    /// Base URL of the verifier asked, e.g. `https://10.0.0.5:9090`.
+    pub target: String,
+    /// `$USER` of the invoking process, if set.
+    pub user: Option<String>,
+    pub succeeded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ShutdownRecord {
+    pub fn new(circuit_id: u64, target: &str, outcome: &Result<()>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            circuit_id,
+            target: target.to_string(),
+            user: std::env::var("USER").ok(),
+            succeeded: outcome.is_ok(),
+            error: outcome.as_ref().err().map(|e| format!("{:#}", e)),
+        }
+    }
+}
+
+/// Append-only log of emergency shutdowns, one JSON object per line.
+pub struct AuditLog {
+    file: File,
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Open `path` for appending, creating it if missing. Done before the
+    /// shutdown is attempted, so an unwritable log stops the shutdown rather
+    /// than letting it go unrecorded.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Append `record` and sync it to disk before returning.
+    pub fn record(&mut self, record: &ShutdownRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|()| self.file.sync_data())
+            .with_context(|| format!("failed to write audit log {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_appended_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("qtop-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        AuditLog::open(&path)
+            .unwrap()
+            .record(&ShutdownRecord::new(1, "http://127.0.0.1:9090", &Ok(())))
+            .unwrap();
+        let failed = Err(anyhow::anyhow!(
+            "no verifier reachable on https://10.0.0.5:9090"
+        ));
+        AuditLog::open(&path)
+            .unwrap()
+            .record(&ShutdownRecord::new(2, "https://10.0.0.5:9090", &failed))
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["circuit_id"], 1);
+        assert_eq!(lines[0]["target"], "http://127.0.0.1:9090");
+        assert_eq!(lines[0]["succeeded"], true);
+        assert!(lines[0].get("error").is_none());
+        assert_eq!(lines[1]["target"], "https://10.0.0.5:9090");
+        assert_eq!(lines[1]["succeeded"], false);
+        assert_eq!(
+            lines[1]["error"],
+            "no verifier reachable on https://10.0.0.5:9090"
+        );
+    }
+}
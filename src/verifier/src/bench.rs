@@ -0,0 +1,137 @@
+use crate::cli::OutputFormat;
+use crate::output::Output;
+use crate::ui::Glyph;
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use serde::Serialize;
+use std::f64::consts::TAU;
+use std::hint::black_box;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Timings of `winding_number` on phase arrays of one size.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub size: usize,
+    pub iters: u32,
+    /// Seed the phases were generated from.
+    pub seed: u64,
+    pub mean_secs: f64,
+    pub median_secs: f64,
+    /// Windings computed per second, from the mean.
+    pub windings_per_sec: f64,
+}
+
+/// The generator synthetic phases are drawn from; the same seed always
+/// yields the same phases.
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Phases sampled at `size` points around a loop that winds three times, so
+/// every step has to be wrapped. Each sample is jittered by up to a quarter
+/// of the mean step, which keeps the winding at three for any `size` above 9.
+pub fn synthetic_phases(size: usize, rng: &mut StdRng) -> Vec<f64> {
+    let step = 3.0 * TAU / size as f64;
+    (0..size)
+        .map(|i| step * i as f64 + rng.random_range(-0.25..=0.25) * step)
+        .collect()
+}
+
+/// Time `iters` winding computations over a synthetic loop of `size`
+/// samples generated from `seed`.
+pub fn measure(size: usize, iters: u32, seed: u64) -> BenchResult {
+    let phases = synthetic_phases(size, &mut rng(seed));
+    // Untimed, so the first sample doesn't pay for cold caches.
+    black_box(qtop_core::winding_number(black_box(&phases)).value);
+    let samples: Vec<Duration> = (0..iters)
+        .map(|_| {
+            let started = Instant::now();
+            black_box(qtop_core::winding_number(black_box(&phases)).value);
+            started.elapsed()
+        })
+        .collect();
+    summarize(size, seed, samples)
+}
+
+fn summarize(size: usize, seed: u64, mut samples: Vec<Duration>) -> BenchResult {
+    samples.sort_unstable();
+    let iters = samples.len() as u32;
+    let mean = samples.iter().sum::<Duration>() / iters;
+    let mid = samples.len() / 2;
+    let median = if samples.len().is_multiple_of(2) {
+        (samples[mid - 1] + samples[mid]) / 2
+    } else {
+        samples[mid]
+    };
+    BenchResult {
+        size,
+        iters,
+        seed,
+        mean_secs: mean.as_secs_f64(),
+        median_secs: median.as_secs_f64(),
+        windings_per_sec: 1.0 / mean.as_secs_f64().max(f64::MIN_POSITIVE),
+    }
+}
+
+pub fn report(results: &[BenchResult], format: OutputFormat, out: &mut Output) -> Result<()> {
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string(results)?)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            for result in results {
+                writer.serialize(result)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Text => {
+            for result in results {
+                writeln!(
+                    out,
+                    "{} {} phases: mean {:?}, median {:?}, {:.0} windings/s ({} iterations)",
+                    Glyph::Summary,
+                    result.size,
+                    Duration::from_secs_f64(result.mean_secs),
+                    Duration::from_secs_f64(result.median_secs),
+                    result.windings_per_sec,
+                    result.iters
+                )?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_loop_winds_three_times() {
+        for seed in 0..20 {
+            for size in [10, 16, 1000] {
+                let phases = synthetic_phases(size, &mut rng(seed));
+                assert_eq!(qtop_core::winding_number(&phases).value, 3);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_phases() {
+        let phases = synthetic_phases(100, &mut rng(7));
+        assert_eq!(phases, synthetic_phases(100, &mut rng(7)));
+        assert_ne!(phases, synthetic_phases(100, &mut rng(8)));
+    }
+
+    #[test]
+    fn summary_uses_mean_and_median() {
+        let samples = [4, 1, 3, 2].map(Duration::from_millis).to_vec();
+        let result = summarize(10, 0, samples);
+        assert_eq!(result.iters, 4);
+        assert_eq!(result.mean_secs, 0.0025);
+        assert_eq!(result.median_secs, 0.0025);
+        assert!((result.windings_per_sec - 400.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,125 @@
+use lru::LruCache;
+use qtop_core::{Circuit, WindingResult};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Distinct phase vectors remembered before the least recently used one is
+/// evicted.
+const CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+
+type PhaseDigest = [u8; 32];
+
+/// Cache hit and miss counts, as shown on `/status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Winding numbers already computed, keyed by a SHA-256 digest of the phase
+/// vector they were computed from.
+///
+/// A changed phase vector has a different digest and so can never be
+/// answered from an entry for the old data; the old entry is dropped as
+/// soon as the change is seen rather than left to age out.
+pub struct WindingCache {
+    inner: Mutex<Inner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Inner {
+    windings: LruCache<PhaseDigest, WindingResult>,
+    /// Digest each circuit's phases had when it was last looked up.
+    circuits: HashMap<u64, PhaseDigest>,
+}
+
+impl Default for WindingCache {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                windings: LruCache::new(CAPACITY),
+                circuits: HashMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+}
+
+impl WindingCache {
+    /// The winding of `circuit`'s phases, computed only if these exact
+    /// phases have not been seen recently.
+    pub fn winding(&self, circuit: &Circuit) -> WindingResult {
+        let digest = phase_digest(&circuit.phases);
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(previous) = inner.circuits.insert(circuit.id, digest) {
+                if previous != digest {
+                    inner.windings.pop(&previous);
+                }
+            }
+            if let Some(&winding) = inner.windings.get(&digest) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return winding;
+            }
+        }
+
+        // Computed without the lock held so other circuits aren't held up.
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let winding = circuit.winding();
+        self.inner.lock().unwrap().windings.put(digest, winding);
+        winding
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.inner.lock().unwrap().windings.len(),
+        }
+    }
+}
+
+fn phase_digest(phases: &[f64]) -> PhaseDigest {
+    let mut hasher = Sha256::new();
+    for phase in phases {
+        hasher.update(phase.to_le_bytes());
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    #[test]
+    fn unchanged_phases_hit_and_changed_phases_recompute() {
+        let cache = WindingCache::default();
+        let mut circuit = Circuit::new(1, vec![0.0, TAU / 3.0, 2.0 * TAU / 3.0]);
+
+        assert_eq!(cache.winding(&circuit).value, 1);
+        assert_eq!(cache.winding(&circuit).value, 1);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                entries: 1
+            }
+        );
+
+        circuit.phases = vec![0.0, -TAU / 3.0, -2.0 * TAU / 3.0];
+        assert_eq!(cache.winding(&circuit).value, -1);
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 2);
+        // The entry for the old phases was dropped, not kept alongside.
+        assert_eq!(stats.entries, 1);
+    }
+}
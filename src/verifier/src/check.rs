@@ -0,0 +1,670 @@
+use crate::cache::WindingCache;
+use crate::cli::OutputFormat;
+use crate::output::{BatchProgress, Output};
+use crate::quorum::{PeerCheck, QuorumVote};
+use crate::ui::Glyph;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use qtop_core::{Circuit, Invariant, VerificationResult};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::future::Future;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinError;
+use tokio::time::Instant;
+use tracing::{debug, error, info, info_span, warn};
+
+/// Result of checking one circuit in a batch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum CheckOutcome {
+    Verified(VerificationResult),
+    /// Verified locally and cross-checked against peer verifiers. The
+    /// result is only valid if the peers reached a quorum on it.
+    CrossChecked {
+        #[serde(flatten)]
+        result: VerificationResult,
+        /// Whether the local verification alone passed.
+        locally_valid: bool,
+        quorum: QuorumVote,
+    },
+    /// The verification itself failed, e.g. the computation panicked.
+    Failed {
+        circuit_id: u64,
+        error: String,
+    },
+    /// The computation did not finish within the configured timeout.
+    TimedOut {
+        circuit_id: u64,
+        timed_out_after_secs: f64,
+    },
+}
+
+impl CheckOutcome {
+    pub fn is_valid(&self) -> bool {
+        self.result().is_some_and(|result| result.valid)
+    }
+
+    /// Whether the circuit could not be verified at all, as opposed to
+    /// being verified and found invalid.
+    pub fn is_error(&self) -> bool {
+        self.result().is_none()
+    }
+
+    pub fn circuit_id(&self) -> u64 {
+        match self {
+            CheckOutcome::Verified(result) | CheckOutcome::CrossChecked { result, .. } => {
+                result.circuit_id
+            }
+            CheckOutcome::Failed { circuit_id, .. } | CheckOutcome::TimedOut { circuit_id, .. } => {
+                *circuit_id
+            }
+        }
+    }
+
+    /// The verification result, if the circuit could be verified.
+    pub fn result(&self) -> Option<&VerificationResult> {
+        match self {
+            CheckOutcome::Verified(result) | CheckOutcome::CrossChecked { result, .. } => {
+                Some(result)
+            }
+            CheckOutcome::Failed { .. } | CheckOutcome::TimedOut { .. } => None,
+        }
+    }
+}
+
+/// How a batch of circuits is checked and reported.
+#[derive(Clone)]
+pub struct CheckOptions {
+    /// Maximum number of circuits verified at once.
+    pub concurrency: usize,
+    /// Invariant each circuit is verified against.
+    pub invariant: Invariant,
+    /// Overrides each circuit's own expected value of the invariant when
+    /// set.
+    pub expected: Option<i64>,
+    /// Allowed distance between the computed and expected value.
+    pub tolerance: u64,
+    /// Warn about windings whose confidence falls below this.
+    pub min_confidence: Option<f64>,
+    /// Give up on a circuit whose verification takes longer than this.
+    pub timeout: Option<Duration>,
+    pub format: OutputFormat,
+    /// Where winding numbers of phases seen before are looked up.
+    pub cache: Option<Arc<WindingCache>>,
+    /// Stop at the first circuit that does not pass, leaving the rest
+    /// unchecked.
+    pub fail_fast: bool,
+    /// Draw a progress bar on stderr rather than logging progress.
+    pub progress_bar: bool,
+}
+
+/// Verify `circuits` with at most `options.concurrency` computations in
+/// flight, reporting each outcome in input order as it becomes available.
+/// With `peers`, every verified result is also put to a quorum vote. With
+/// `options.fail_fast` the outcomes end at the first circuit that does not
+/// pass; verifications still in flight then are abandoned.
+pub async fn check_batch(
+    circuits: Vec<Circuit>,
+    options: CheckOptions,
+    peers: Option<&PeerCheck>,
+    out: &mut Output,
+) -> Result<Vec<CheckOutcome>> {
+    let mut progress = BatchProgress::new(circuits.len(), options.progress_bar);
+    let mut outcomes = stream::iter(circuits)
+        .map(|circuit| async {
+            let started = Instant::now();
+            let mut outcome = check_circuit(circuit, &options).await;
+            if let (Some(peers), CheckOutcome::Verified(result)) = (peers, &outcome) {
+                outcome = cross_check(result.clone(), peers).await;
+            }
+            (outcome, started.elapsed())
+        })
+        .buffered(options.concurrency);
+
+    let mut reporter = Reporter::new(options.format, out);
+    let mut results = Vec::new();
+    while let Some((outcome, elapsed)) = outcomes.next().await {
+        progress.suspend(|| reporter.report(&outcome, elapsed))?;
+        progress.inc();
+        let stop = options.fail_fast && !outcome.is_valid();
+        results.push(outcome);
+        if stop {
+            break;
+        }
+    }
+    progress.finish();
+    Ok(results)
+}
+
+async fn check_circuit(mut circuit: Circuit, options: &CheckOptions) -> CheckOutcome {
+    match (options.expected, options.invariant) {
+        (Some(expected), Invariant::Winding) => circuit.expected_winding = Some(expected),
+        (Some(expected), Invariant::Chern) => circuit.expected_chern = Some(expected),
+        (None, _) => {}
+    }
+
+    verify_isolated(
+        circuit,
+        options.invariant,
+        options.tolerance,
+        options.min_confidence,
+        options.timeout,
+        options.cache.clone(),
+    )
+    .await
+}
+
+async fn cross_check(mut result: VerificationResult, peers: &PeerCheck) -> CheckOutcome {
+    let quorum = peers.vote(&result).await;
+    if !quorum.reached {
+        warn!(
+            circuit_id = result.circuit_id,
+            agreeing = quorum.agreeing,
+            required = quorum.required,
+            "peers did not reach a quorum"
+        );
+    }
+    let locally_valid = result.valid;
+    result.valid &= quorum.reached;
+    CheckOutcome::CrossChecked {
+        result,
+        locally_valid,
+        quorum,
+    }
+}
+
+/// Verify `circuit` on the blocking pool so a panic or a runaway
+/// computation only affects this circuit's outcome. On timeout the
+/// computation is abandoned rather than cancelled: it keeps its blocking
+/// thread until it returns, but nothing waits on it. A winding less
+/// confident than `min_confidence` is logged as a warning.
+pub async fn verify_isolated(
+    circuit: Circuit,
+    invariant: Invariant,
+    tolerance: u64,
+    min_confidence: Option<f64>,
+    timeout: Option<Duration>,
+    cache: Option<Arc<WindingCache>>,
+) -> CheckOutcome {
+    let circuit_id = circuit.id;
+    if invariant == Invariant::Winding {
+        if let Err(e) = circuit.ensure_phase_loop() {
+            warn!(circuit_id, error = %e, "circuit cannot be verified");
+            return CheckOutcome::Failed {
+                circuit_id,
+                error: e.to_string(),
+            };
+        }
+    }
+    let span = info_span!("check_circuit", circuit_id, %invariant);
+    let verification = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            verify(
+                &circuit,
+                invariant,
+                tolerance,
+                min_confidence,
+                cache.as_deref(),
+            )
+        })
+    });
+    await_verification(circuit_id, verification, timeout).await
+}
+
+async fn await_verification(
+    circuit_id: u64,
+    verification: impl Future<Output = Result<VerificationResult, JoinError>>,
+    timeout: Option<Duration>,
+) -> CheckOutcome {
+    let joined = match timeout {
+        Some(limit) => match tokio::time::timeout(limit, verification).await {
+            Ok(joined) => joined,
+            Err(_) => {
+                warn!(circuit_id, timeout = ?limit, "verification timed out");
+                return CheckOutcome::TimedOut {
+                    circuit_id,
+                    timed_out_after_secs: limit.as_secs_f64(),
+                };
+            }
+        },
+        None => verification.await,
+    };
+
+    match joined {
+        Ok(result) => CheckOutcome::Verified(result),
+        Err(e) => {
+            error!(circuit_id, error = %e, "verification failed");
+            CheckOutcome::Failed {
+                circuit_id,
+                error: e.to_string(),
+            }
+        }
+    }
+}
+
+fn verify(
+    circuit: &Circuit,
+    invariant: Invariant,
+    tolerance: u64,
+    min_confidence: Option<f64>,
+    cache: Option<&WindingCache>,
+) -> VerificationResult {
+    match invariant {
+        Invariant::Winding => debug!(samples = circuit.phases.len(), "checking winding number"),
+        Invariant::Chern => debug!(
+            plaquettes = circuit.berry_flux.len(),
+            chern_number = circuit.chern_number(),
+            "checking Chern number"
+        ),
+    }
+
+    let result = match invariant {
+        Invariant::Winding => {
+            let winding = match cache {
+                Some(cache) => cache.winding(circuit),
+                None => circuit.winding(),
+            };
+            if min_confidence.is_some_and(|min| winding.confidence < min) {
+                warn!(
+                    confidence = winding.confidence,
+                    min_confidence,
+                    "winding may be aliased: a phase step came close to π, sample the loop more finely"
+                );
+            }
+            circuit.verify_known_winding(winding, tolerance)
+        }
+        Invariant::Chern => circuit.verify_invariant(invariant, tolerance),
+    };
+    if result.valid {
+        info!(
+            value = result.winding_number,
+            expected = result.expected_winding,
+            "{} valid",
+            label(invariant)
+        );
+    } else {
+        warn!(
+            value = result.winding_number,
+            expected = result.expected_winding,
+            "{} violation detected",
+            label(invariant)
+        );
+    }
+    result
+}
+
+/// Human-readable name of an invariant, as used in report lines.
+fn label(invariant: Invariant) -> &'static str {
+    match invariant {
+        Invariant::Winding => "winding number",
+        Invariant::Chern => "Chern number",
+    }
+}
+
+/// One line of `--format csv` output.
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    circuit_id: u64,
+    winding_number: Option<i64>,
+    expected: Option<i64>,
+    valid: bool,
+    duration_ms: f64,
+    /// Why the circuit could not be verified, if it could not.
+    error: Option<Cow<'a, str>>,
+    /// Appended after the original columns so they keep their positions.
+    confidence: Option<f64>,
+}
+
+impl<'a> CsvRow<'a> {
+    fn new(outcome: &'a CheckOutcome, elapsed: Duration) -> Self {
+        // Microsecond resolution is plenty and keeps the column readable.
+        let duration_ms = elapsed.as_micros() as f64 / 1000.0;
+        match outcome {
+            CheckOutcome::Verified(result) | CheckOutcome::CrossChecked { result, .. } => CsvRow {
+                circuit_id: result.circuit_id,
+                winding_number: Some(result.winding_number),
+                expected: result.expected_winding,
+                valid: result.valid,
+                duration_ms,
+                error: None,
+                confidence: result.confidence,
+            },
+            CheckOutcome::Failed { circuit_id, error } => CsvRow {
+                circuit_id: *circuit_id,
+                winding_number: None,
+                expected: None,
+                valid: false,
+                duration_ms,
+                error: Some(Cow::Borrowed(error)),
+                confidence: None,
+            },
+            CheckOutcome::TimedOut {
+                circuit_id,
+                timed_out_after_secs,
+            } => CsvRow {
+                circuit_id: *circuit_id,
+                winding_number: None,
+                expected: None,
+                valid: false,
+                duration_ms,
+                error: Some(Cow::Owned(format!(
+                    "timed out after {}s",
+                    timed_out_after_secs
+                ))),
+                confidence: None,
+            },
+        }
+    }
+}
+
+/// Report a verified result; `note` is appended to the expectation and
+/// the winding's confidence, if it has one.
+fn report_verified(
+    out: &mut impl Write,
+    result: &VerificationResult,
+    note: &str,
+) -> io::Result<()> {
+    let confidence = match result.confidence {
+        Some(confidence) => format!(", confidence {:.2}", confidence),
+        None => String::new(),
+    };
+    if result.valid {
+        writeln!(
+            out,
+            "{} Circuit {} {} {} is valid ({}{}{})",
+            Glyph::Ok,
+            result.circuit_id,
+            label(result.invariant),
+            result.winding_number,
+            expectation(result),
+            confidence,
+            note
+        )
+    } else {
+        writeln!(
+            out,
+            "{} Circuit {} {} violation detected: got {}, {}{}{}",
+            Glyph::Fail,
+            result.circuit_id,
+            label(result.invariant),
+            result.winding_number,
+            expectation(result),
+            confidence,
+            note
+        )
+    }
+}
+
+/// Human-readable expectation a result was checked against.
+fn expectation(result: &VerificationResult) -> String {
+    match result.expected_winding {
+        Some(expected) => format!("expected {}", expected),
+        None => "expected non-zero".to_string(),
+    }
+}
+
+/// Writes outcomes in the requested format as they arrive.
+enum Reporter<'a> {
+    Text(&'a mut Output),
+    Json(&'a mut Output),
+    Csv(Box<csv::Writer<&'a mut Output>>),
+}
+
+impl<'a> Reporter<'a> {
+    fn new(format: OutputFormat, out: &'a mut Output) -> Self {
+        match format {
+            OutputFormat::Text => Reporter::Text(out),
+            OutputFormat::Json => Reporter::Json(out),
+            OutputFormat::Csv => Reporter::Csv(Box::new(csv::Writer::from_writer(out))),
+        }
+    }
+
+    fn report(&mut self, outcome: &CheckOutcome, elapsed: Duration) -> Result<()> {
+        match self {
+            Reporter::Text(out) => {
+                report_text(out, outcome)?;
+                out.flush()?;
+            }
+            Reporter::Json(out) => {
+                writeln!(out, "{}", serde_json::to_string(outcome)?)?;
+                out.flush()?;
+            }
+            Reporter::Csv(writer) => {
+                writer.serialize(CsvRow::new(outcome, elapsed))?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn report_text(out: &mut impl Write, outcome: &CheckOutcome) -> io::Result<()> {
+    match outcome {
+        CheckOutcome::Verified(result) => report_verified(out, result, ""),
+        CheckOutcome::CrossChecked { result, quorum, .. } => {
+            if quorum.reached {
+                let note = format!(
+                    "; {} of {} peers agree",
+                    quorum.agreeing,
+                    quorum.peers.len()
+                );
+                report_verified(out, result, &note)?;
+            } else {
+                writeln!(
+                    out,
+                    "{} Circuit {} {} {} has no quorum: {} of {} peers agree (need {})",
+                    Glyph::Fail,
+                    result.circuit_id,
+                    label(result.invariant),
+                    result.winding_number,
+                    quorum.agreeing,
+                    quorum.peers.len(),
+                    quorum.required
+                )?;
+            }
+            if quorum.is_split() {
+                for answer in &quorum.peers {
+                    match (answer.winding_number, &answer.error) {
+                        (Some(winding), _) => writeln!(out, "    {}: {}", answer.peer, winding)?,
+                        (None, error) => writeln!(
+                            out,
+                            "    {}: no answer ({})",
+                            answer.peer,
+                            error.as_deref().unwrap_or("unknown error")
+                        )?,
+                    }
+                }
+            }
+            Ok(())
+        }
+        CheckOutcome::TimedOut {
+            circuit_id,
+            timed_out_after_secs,
+        } => writeln!(
+            out,
+            "{} Circuit {} verification timed out after {}s",
+            Glyph::Fail,
+            circuit_id,
+            timed_out_after_secs
+        ),
+        CheckOutcome::Failed { circuit_id, error } => writeln!(
+            out,
+            "{} Circuit {} verification failed: {}",
+            Glyph::Fail,
+            circuit_id,
+            error
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    #[tokio::test]
+    async fn batch_preserves_input_order() {
+        let circuits: Vec<Circuit> = (0..32)
+            .rev()
+            .map(|id| {
+                let samples = 8 + id as usize * 16;
+                let phases = (0..samples)
+                    .map(|i| TAU * i as f64 / samples as f64)
+                    .collect();
+                Circuit::new(id, phases)
+            })
+            .collect();
+
+        let options = CheckOptions {
+            concurrency: 4,
+            invariant: Invariant::Winding,
+            expected: None,
+            tolerance: 0,
+            min_confidence: None,
+            timeout: None,
+            format: OutputFormat::Json,
+            cache: None,
+            fail_fast: false,
+            progress_bar: false,
+        };
+        let outcomes = check_batch(circuits, options, None, &mut Output::open(None).unwrap())
+            .await
+            .unwrap();
+        let ids: Vec<u64> = outcomes
+            .iter()
+            .map(|outcome| match outcome {
+                CheckOutcome::Verified(result) => result.circuit_id,
+                other => panic!("unexpected outcome {:?}", other),
+            })
+            .collect();
+        assert_eq!(ids, (0..32).rev().collect::<Vec<_>>());
+        assert!(outcomes.iter().all(CheckOutcome::is_valid));
+    }
+
+    #[tokio::test]
+    async fn fail_fast_stops_at_the_first_failure() {
+        let winding_once = (0..4).map(|i| TAU * i as f64 / 4.0).collect::<Vec<_>>();
+        let circuits: Vec<Circuit> = (0..8)
+            .map(|id| match id {
+                // Constant phase: a winding of 0 is a violation.
+                3 => Circuit::new(id, vec![1.0; 4]),
+                _ => Circuit::new(id, winding_once.clone()),
+            })
+            .collect();
+        let options = CheckOptions {
+            concurrency: 2,
+            invariant: Invariant::Winding,
+            expected: None,
+            tolerance: 0,
+            min_confidence: None,
+            timeout: None,
+            format: OutputFormat::Json,
+            cache: None,
+            fail_fast: true,
+            progress_bar: false,
+        };
+
+        let outcomes = check_batch(circuits, options, None, &mut Output::open(None).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[3].circuit_id(), 3);
+        assert!(!outcomes[3].is_valid());
+    }
+
+    #[test]
+    fn csv_rows_quote_awkward_fields() {
+        let failed = CheckOutcome::Failed {
+            circuit_id: 4,
+            error: "task panicked: \"bad\", really".to_string(),
+        };
+        let verified = CheckOutcome::Verified(VerificationResult {
+            circuit_id: 5,
+            invariant: Invariant::Winding,
+            winding_number: -1,
+            expected_winding: None,
+            confidence: Some(0.75),
+            valid: true,
+        });
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .serialize(CsvRow::new(&failed, Duration::from_micros(1500)))
+            .unwrap();
+        writer
+            .serialize(CsvRow::new(&verified, Duration::from_millis(2)))
+            .unwrap();
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            written,
+            "circuit_id,winding_number,expected,valid,duration_ms,error,confidence\n\
+             4,,,false,1.5,\"task panicked: \"\"bad\"\", really\",\n\
+             5,-1,,true,2.0,,0.75\n"
+        );
+    }
+
+    /// Everything logged at warning level while running `f`.
+    fn warnings_during(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::WARN)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let logs = captured.0.lock().unwrap().clone();
+        String::from_utf8(logs).unwrap()
+    }
+
+    #[test]
+    fn windings_below_min_confidence_are_warned_about() {
+        // Steps of about 2.09 rad leave a confidence of about 0.33.
+        let circuit = Circuit::new(1, vec![0.0, 2.09, 4.18]).with_expected_winding(1);
+        let check = |min_confidence| {
+            let mut result = None;
+            let logs = warnings_during(|| {
+                result = Some(verify(
+                    &circuit,
+                    Invariant::Winding,
+                    0,
+                    Some(min_confidence),
+                    None,
+                ));
+            });
+            (result.unwrap(), logs)
+        };
+
+        let (result, logs) = check(0.5);
+        assert!(logs.contains("winding may be aliased"), "{}", logs);
+        let confidence = result.confidence.unwrap();
+        assert!((0.3..0.35).contains(&confidence), "{}", confidence);
+        let (_, logs) = check(0.25);
+        assert!(!logs.contains("winding may be aliased"), "{}", logs);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_verification_times_out() {
+        let never = std::future::pending();
+        let outcome = await_verification(9, never, Some(Duration::from_secs(1))).await;
+        assert!(matches!(
+            outcome,
+            CheckOutcome::TimedOut { circuit_id: 9, .. }
+        ));
+        assert!(outcome.is_error());
+    }
+}
@@ -0,0 +1,812 @@
+use crate::quorum::Quorum;
+use crate::retry::RetryPolicy;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use qtop_core::Invariant;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "qtop-verifier", version)]
+#[command(about = "Quantum topological winding number verifier", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Use ASCII markers like `[OK]` and `[FAIL]` instead of emoji
+    #[arg(long, global = true)]
+    pub no_emoji: bool,
+
+    /// Config file supplying defaults for these flags [default: ./qtop.toml
+    /// if present]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Format of the log lines written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+}
+
+/// Flags never read from the environment: a stray variable must not be
+/// able to skip or answer a shutdown's confirmation.
+const NO_ENV: &[&str] = &["force", "confirm"];
+
+/// The command-line definition, with every flag also read from a `QTOP_*`
+/// environment variable named after it (`--alert-webhook` from
+/// `QTOP_ALERT_WEBHOOK`) when not given on the command line.
+pub fn command() -> clap::Command {
+    with_env(Cli::command())
+}
+
+fn with_env(command: clap::Command) -> clap::Command {
+    command
+        .mut_args(|arg| match arg.get_long() {
+            Some(long) if !NO_ENV.contains(&long) => {
+                let var = format!("QTOP_{}", long.replace('-', "_").to_uppercase());
+                arg.env(var)
+            }
+            _ => arg,
+        })
+        .mut_subcommands(with_env)
+}
+
+/// Parse `argv` against [`command`], so unset flags fall back to their
+/// environment variables before any config file or default applies.
+pub fn try_parse_from<I, T>(argv: I) -> Result<Cli, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = command().try_get_matches_from(argv)?;
+    Cli::from_arg_matches(&matches)
+}
+
+/// Port a monitor listens on, and `shutdown` contacts, unless configured.
+pub const DEFAULT_PORT: u16 = 9090;
+
+/// Address a monitor serves on unless configured: loopback only, so that
+/// exposing it further is always a deliberate choice.
+pub const DEFAULT_BIND: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a shutdown waits for a circuit's running verifications.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Continuously verify circuits and serve their status over HTTP
+    Monitor(MonitorArgs),
+    /// Verify the winding numbers of one or more circuits
+    Check(CheckArgs),
+    /// Ask a running monitor to stop monitoring a circuit
+    Shutdown(ShutdownArgs),
+    /// Show the recorded verification history of a circuit
+    History(HistoryArgs),
+    /// Compare the winding numbers of two circuits, e.g. a golden circuit
+    /// against a refactored candidate
+    Diff(DiffArgs),
+    /// Measure winding computation throughput on synthetic circuits
+    Bench(BenchArgs),
+    /// List the circuits defined in a circuits file or directory
+    List(ListArgs),
+    /// Re-run the verifications recorded by `check --proof` and confirm
+    /// their results still hold
+    VerifyProof(VerifyProofArgs),
+    /// Print a shell completion script, e.g. `source <(qtop-verifier
+    /// completions bash)`
+    Completions(CompletionsArgs),
+    /// Verify built-in reference circuits of known winding, to confirm an
+    /// installed binary computes windings correctly
+    Selftest,
+}
+
+#[derive(Args, Clone)]
+pub struct MonitorArgs {
+    /// Address to serve on, IPv4 or IPv6; `0.0.0.0` or `::` for every
+    /// interface [default: 127.0.0.1]
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<IpAddr>,
+
+    /// Port to serve status and metrics on [default: 9090]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Also serve the gRPC interface (`proto/qtop.proto`) on this port
+    #[arg(long, value_name = "PORT")]
+    pub grpc_port: Option<u16>,
+
+    /// Poll interval, in seconds (e.g. `0.5`) or as a duration (e.g. `5m`)
+    /// [default: 10]
+    #[arg(long, value_parser = parse_duration)]
+    pub interval: Option<Duration>,
+
+    /// Give up on a circuit whose verification takes longer than this
+    #[arg(long, value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// JSON or TOML file of circuit definitions to monitor
+    #[arg(long)]
+    pub circuits: Option<PathBuf>,
+
+    /// Write each check's result to stdout as a JSON line, with a
+    /// timestamp, for streaming consumers; other output moves to stderr
+    #[arg(long)]
+    pub stream: bool,
+
+    /// How long a shutdown waits for a circuit's running verifications to
+    /// finish before removing it anyway [default: 10]
+    #[arg(long, value_parser = parse_duration)]
+    pub drain_timeout: Option<Duration>,
+
+    /// Answer every HTTP request within this long, or fail it with a 408,
+    /// and close connections that send no request headers within it. Keep
+    /// it above `--drain-timeout` so shutdowns can report how they went
+    #[arg(long, default_value = "30s", value_parser = parse_duration)]
+    pub request_timeout: Duration,
+
+    /// Most HTTP connections open at once; more are answered with a 503
+    /// and closed rather than queued
+    #[arg(long, value_name = "N", default_value = "256")]
+    pub max_connections: NonZeroUsize,
+
+    /// POST a JSON alert to this URL when a circuit starts violating its
+    /// expected winding
+    #[arg(long, value_name = "URL", value_parser = reqwest::Url::parse)]
+    pub alert_webhook: Option<reqwest::Url>,
+
+    /// Most violation alerts sent per minute. Alerts beyond that are
+    /// dropped and counted in a once-a-minute summary instead
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub alert_rate: u32,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    /// Recompute every winding number instead of reusing the result for
+    /// circuits whose phases have not changed
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// PEM certificate chain; serves the endpoints over HTTPS together with
+    /// `--tls-key`
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for `--tls-cert`
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Circuit IDs to check, space- or comma-separated. `-` reads phase
+    /// samples for a single ad-hoc circuit from stdin. May be omitted with
+    /// `--circuits-dir` to check every circuit found
+    #[arg(required_unless_present = "circuits_dir", value_delimiter = ',')]
+    pub ids: Vec<CircuitSelector>,
+
+    /// JSON or TOML file of circuit definitions
+    #[arg(long)]
+    pub circuits: Option<PathBuf>,
+
+    /// Directory searched recursively for JSON and TOML circuit files
+    #[arg(long, value_name = "PATH", conflicts_with = "circuits")]
+    pub circuits_dir: Option<PathBuf>,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Which circuit data to compute the winding from
+    #[arg(long, value_enum, default_value_t = InputKind::Phases)]
+    pub input_kind: InputKind,
+
+    /// Maximum number of circuits verified in parallel [default: number of CPUs]
+    #[arg(long)]
+    pub concurrency: Option<NonZeroUsize>,
+
+    /// Topological invariant to verify: `winding` (from the phase loop) or
+    /// `chern` (from the circuit's `berry_flux`)
+    #[arg(long, default_value = "winding")]
+    pub invariant: Invariant,
+
+    /// Value of the invariant every checked circuit must have, overriding
+    /// the circuit file's `expected_winding` or `expected_chern`
+    #[arg(long, allow_negative_numbers = true)]
+    pub expected: Option<i64>,
+
+    /// Accept values within this distance of the expected one
+    #[arg(long, default_value_t = 0)]
+    pub tolerance: u64,
+
+    /// Verify only the phase samples START..END (END exclusive) of each
+    /// circuit, closed into a loop of their own, e.g. to find where a
+    /// defect enters a long loop. Drops the circuits' expected windings
+    #[arg(long, value_name = "START..END", value_parser = parse_range)]
+    pub range: Option<Range<usize>>,
+
+    /// Warn when a winding's confidence falls below this, 0 to 1. The
+    /// confidence is `1 - largest phase step / π`; near 0, some step was
+    /// close enough to π that the winding could be off by one
+    #[arg(long, value_name = "C", default_value_t = 0.25, value_parser = parse_confidence)]
+    pub min_confidence: f64,
+
+    /// Give up on a circuit whose verification takes longer than this, in
+    /// seconds or as a duration
+    #[arg(long, value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// SQLite database recording every circuit check, created if missing
+    #[arg(long)]
+    pub db: Option<PathBuf>,
+
+    /// Stop at the first circuit that fails verification instead of
+    /// checking the rest
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Keep running and re-check whenever the `--circuits` file changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Comma-separated base URLs of peer verifiers to cross-check each
+    /// result against, via their `/circuit/{id}` endpoint
+    #[arg(long, value_name = "URLS", value_delimiter = ',', value_parser = reqwest::Url::parse)]
+    pub peers: Option<Vec<reqwest::Url>>,
+
+    /// How many peers must agree on the winding for a circuit to pass,
+    /// e.g. `2of3` [default: a majority]
+    #[arg(long, requires = "peers")]
+    pub quorum: Option<Quorum>,
+
+    /// Recompute every winding number instead of reusing the result for
+    /// phases already seen
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Write a JSON proof of each verification, with digests of its inputs,
+    /// for `verify-proof` to re-check later
+    #[arg(long, value_name = "PATH")]
+    pub proof: Option<PathBuf>,
+
+    /// Write results to this file instead of stdout; progress messages then
+    /// go to stderr
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ShutdownArgs {
+    pub id: u64,
+
+    /// Host name or address of the monitor to contact, e.g. `::1` for a
+    /// monitor started with `--bind ::1` [default: 127.0.0.1]
+    #[arg(long, value_name = "HOST")]
+    pub host: Option<String>,
+
+    /// Port of the monitor to contact [default: 9090]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Contact the monitor over HTTPS, for one started with `--tls-cert`
+    #[arg(long)]
+    pub tls: bool,
+
+    /// PEM CA certificate to trust for `--tls`, in addition to the
+    /// built-in roots, e.g. the monitor's own self-signed certificate
+    #[arg(long, value_name = "PATH", requires = "tls")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Accept any certificate for `--tls`, skipping verification entirely
+    #[arg(long, requires = "tls", conflicts_with = "ca_cert")]
+    pub insecure: bool,
+
+    /// Append a JSON record of this shutdown, and whether it succeeded, to
+    /// this file
+    #[arg(long, value_name = "PATH")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Confirm the shutdown with the `shutdown_token` from the config file
+    #[arg(long, value_name = "TOKEN")]
+    pub confirm: Option<String>,
+
+    /// Shut down without asking for confirmation
+    #[arg(long, conflicts_with = "confirm")]
+    pub force: bool,
+
+    /// Check the circuit is being monitored and report what would happen,
+    /// without shutting anything down
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    /// Secret `--confirm` must match, taken from the config file.
+    #[arg(skip)]
+    pub shutdown_token: Option<String>,
+}
+
+/// Retries for requests that fail to connect or time out.
+#[derive(Args, Clone)]
+pub struct RetryArgs {
+    /// Times to retry a request that failed to connect or timed out
+    #[arg(long, default_value_t = 3)]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubling for each one after it
+    #[arg(long, default_value = "200ms", value_parser = parse_duration)]
+    pub retry_delay: Duration,
+}
+
+impl RetryArgs {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: self.retry_delay,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    pub id: u64,
+
+    /// SQLite database written by `check --db`
+    #[arg(long)]
+    pub db: PathBuf,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write results to this file instead of stdout; progress messages then
+    /// go to stderr
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    pub circuit_a: u64,
+    pub circuit_b: u64,
+
+    /// JSON or TOML file defining both circuits
+    #[arg(long)]
+    pub circuits: Option<PathBuf>,
+
+    /// Which circuit data to compute the windings from
+    #[arg(long, value_enum, default_value_t = InputKind::Phases)]
+    pub input_kind: InputKind,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write results to this file instead of stdout; progress messages then
+    /// go to stderr
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Comma-separated phase-array lengths to benchmark
+    #[arg(long, value_delimiter = ',', default_value = "100,1000,10000")]
+    pub sizes: Vec<usize>,
+
+    /// Timed winding computations per size
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iters: u32,
+
+    /// Seed for the synthetic phases, to reproduce an earlier run [default:
+    /// random, and printed]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write results to this file instead of stdout; progress messages then
+    /// go to stderr
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// JSON or TOML file of circuit definitions
+    #[arg(long)]
+    pub circuits: Option<PathBuf>,
+
+    /// Directory searched recursively for JSON and TOML circuit files
+    #[arg(long, value_name = "PATH", conflicts_with = "circuits")]
+    pub circuits_dir: Option<PathBuf>,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write results to this file instead of stdout; progress messages then
+    /// go to stderr
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct VerifyProofArgs {
+    /// Proof file written by `check --proof`
+    pub path: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    #[arg(value_enum)]
+    pub shell: clap_complete::Shell,
+}
+
+// Flags left unset on the command line may still be filled in from the
+// config file; these apply the built-in defaults to whatever remains.
+
+impl MonitorArgs {
+    pub fn bind(&self) -> IpAddr {
+        self.bind.unwrap_or(DEFAULT_BIND)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval.unwrap_or(DEFAULT_INTERVAL)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT)
+    }
+}
+
+impl CheckArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Text)
+    }
+}
+
+impl ShutdownArgs {
+    /// `host:port` of the monitor, with IPv6 addresses in brackets. A
+    /// monitor bound to every interface is reached through loopback.
+    pub fn authority(&self) -> String {
+        let host = self.host.as_deref().unwrap_or("127.0.0.1");
+        match host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => {
+                let loopback = match ip {
+                    IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                    IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+                };
+                SocketAddr::new(loopback, self.port()).to_string()
+            }
+            Ok(ip) => SocketAddr::new(ip, self.port()).to_string(),
+            Err(_) => format!("{}:{}", host, self.port()),
+        }
+    }
+
+    /// Scheme and authority of the monitor, e.g. `https://[::1]:9090`.
+    pub fn base_url(&self) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        format!("{}://{}", scheme, self.authority())
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+}
+
+impl HistoryArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Text)
+    }
+}
+
+impl DiffArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Text)
+    }
+}
+
+impl ListArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Text)
+    }
+}
+
+impl BenchArgs {
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event, with its fields, level and target
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputKind {
+    /// Sampled phases from the circuit's `phases`
+    Phases,
+    /// Accumulated phase of the circuit's `braiding` matrices
+    Braiding,
+}
+
+/// Circuit id used for phase data read from stdin.
+pub const STDIN_CIRCUIT_ID: u64 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitSelector {
+    Id(u64),
+    Stdin,
+}
+
+impl std::str::FromStr for CircuitSelector {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "-" => Ok(CircuitSelector::Stdin),
+            id => id.parse().map(CircuitSelector::Id),
+        }
+    }
+}
+
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let interval = match value.parse::<f64>() {
+        Ok(secs) => Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())?,
+        Err(_) => humantime::parse_duration(value).map_err(|e| e.to_string())?,
+    };
+    if interval.is_zero() {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(interval)
+}
+
+fn parse_range(value: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("invalid range `{}`: expected e.g. `1000..2000`", value);
+    let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+    let start: usize = start.trim().parse().map_err(|_| invalid())?;
+    let end: usize = end.trim().parse().map_err(|_| invalid())?;
+    if start >= end {
+        return Err(format!(
+            "invalid range `{}`: the end must come after the start",
+            value
+        ));
+    }
+    Ok(start..end)
+}
+
+fn parse_confidence(value: &str) -> Result<f64, String> {
+    let confidence: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(0.0..=1.0).contains(&confidence) {
+        return Err("confidence must be between 0 and 1".to_string());
+    }
+    Ok(confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn flags_fall_back_to_qtop_environment_variables() {
+        // Rerun this test in a child process with the variables set there,
+        // leaving the environment of the parallel test harness untouched.
+        if std::env::var_os("QTOP_ENV_TEST_CHILD").is_none() {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "cli::tests::flags_fall_back_to_qtop_environment_variables",
+                ])
+                .env("QTOP_ENV_TEST_CHILD", "1")
+                .env("QTOP_INTERVAL", "30")
+                .env("QTOP_NO_CACHE", "true")
+                .env("QTOP_FORCE", "true")
+                .env("QTOP_CONFIRM", "s3cret")
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stdout)
+            );
+            return;
+        }
+
+        let monitor = |argv: &[&str]| match try_parse_from(argv).unwrap().command {
+            Command::Monitor(args) => args,
+            _ => panic!("expected monitor subcommand"),
+        };
+
+        let args = monitor(&["qtop-verifier", "monitor"]);
+        assert_eq!(args.interval(), Duration::from_secs(30));
+        assert!(args.no_cache);
+        let args = monitor(&["qtop-verifier", "monitor", "--interval", "5"]);
+        assert_eq!(args.interval(), Duration::from_secs(5));
+
+        let Command::Shutdown(args) = try_parse_from(["qtop-verifier", "shutdown", "7"])
+            .unwrap()
+            .command
+        else {
+            panic!("expected shutdown subcommand");
+        };
+        assert!(!args.force);
+        assert_eq!(args.confirm, None);
+    }
+
+    #[test]
+    fn duration_accepts_fractional_seconds() {
+        assert_eq!(parse_duration("0.25"), Ok(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn duration_accepts_humantime_durations() {
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1m 30s"), Ok(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn duration_rejects_zero_and_negative() {
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("-1").is_err());
+    }
+
+    #[test]
+    fn shutdown_tls_options_require_tls() {
+        let shutdown = |args: &[&str]| {
+            let argv = ["qtop-verifier", "shutdown", "7"]
+                .iter()
+                .chain(args)
+                .copied();
+            Cli::try_parse_from(argv.collect::<Vec<_>>()).map(|cli| match cli.command {
+                Command::Shutdown(args) => args.base_url(),
+                _ => unreachable!(),
+            })
+        };
+        assert_eq!(shutdown(&[]).unwrap(), "http://127.0.0.1:9090");
+        assert_eq!(shutdown(&["--tls"]).unwrap(), "https://127.0.0.1:9090");
+        assert!(shutdown(&["--ca-cert", "ca.pem"]).is_err());
+        assert!(shutdown(&["--insecure"]).is_err());
+        assert!(shutdown(&["--tls", "--ca-cert", "ca.pem", "--insecure"]).is_err());
+        assert!(shutdown(&["--tls", "--ca-cert", "ca.pem"]).is_ok());
+    }
+
+    #[test]
+    fn shutdown_authority_brackets_ipv6_hosts() {
+        let authority = |args: &[&str]| {
+            let argv = ["qtop-verifier", "shutdown", "7"]
+                .iter()
+                .chain(args)
+                .copied();
+            let cli = Cli::try_parse_from(argv.collect::<Vec<_>>()).unwrap();
+            let Command::Shutdown(args) = cli.command else {
+                unreachable!()
+            };
+            args.authority()
+        };
+        assert_eq!(authority(&[]), "127.0.0.1:9090");
+        assert_eq!(authority(&["--host", "::1", "-p", "9191"]), "[::1]:9191");
+        assert_eq!(authority(&["--host", "0.0.0.0"]), "127.0.0.1:9090");
+        assert_eq!(authority(&["--host", "::"]), "[::1]:9090");
+        assert_eq!(
+            authority(&["--host", "verifier.internal"]),
+            "verifier.internal:9090"
+        );
+    }
+
+    #[test]
+    fn range_parses_start_and_exclusive_end() {
+        assert_eq!(parse_range("1000..2000"), Ok(1000..2000));
+        assert!(parse_range("2000..1000").is_err());
+        assert!(parse_range("5..5").is_err());
+        assert!(parse_range("1000-2000").is_err());
+        assert!(parse_range("..2000").is_err());
+    }
+
+    #[test]
+    fn confidence_must_be_between_zero_and_one() {
+        assert_eq!(parse_confidence("0.5"), Ok(0.5));
+        assert_eq!(parse_confidence("1"), Ok(1.0));
+        assert!(parse_confidence("1.5").is_err());
+        assert!(parse_confidence("-0.1").is_err());
+        assert!(parse_confidence("high").is_err());
+    }
+
+    #[test]
+    fn check_accepts_comma_separated_and_stdin_ids() {
+        let cli = Cli::try_parse_from(["qtop-verifier", "check", "1,2", "3", "-"]).unwrap();
+        let Command::Check(args) = cli.command else {
+            panic!("expected check subcommand");
+        };
+        assert_eq!(
+            args.ids,
+            vec![
+                CircuitSelector::Id(1),
+                CircuitSelector::Id(2),
+                CircuitSelector::Id(3),
+                CircuitSelector::Stdin,
+            ]
+        );
+    }
+
+    #[test]
+    fn no_emoji_is_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from(["qtop-verifier", "shutdown", "7", "--no-emoji"]).unwrap();
+        assert!(cli.no_emoji);
+    }
+
+    #[test]
+    fn completions_cover_every_subcommand() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut command(),
+            "qtop-verifier",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        for subcommand in command().get_subcommands() {
+            assert!(script.contains(subcommand.get_name()));
+        }
+    }
+
+    #[test]
+    fn log_format_defaults_to_text() {
+        let cli = Cli::try_parse_from(["qtop-verifier", "list"]).unwrap();
+        assert_eq!(cli.log_format, LogFormat::Text);
+        let argv = ["qtop-verifier", "monitor", "--log-format", "json"];
+        assert_eq!(
+            Cli::try_parse_from(argv).unwrap().log_format,
+            LogFormat::Json
+        );
+    }
+
+    #[test]
+    fn tls_cert_and_key_must_be_given_together() {
+        let monitor = |args: &[&str]| {
+            let argv = ["qtop-verifier", "monitor"].iter().chain(args).copied();
+            Cli::try_parse_from(argv.collect::<Vec<_>>())
+        };
+        assert!(monitor(&["--tls-cert", "cert.pem"]).is_err());
+        assert!(monitor(&["--tls-key", "key.pem"]).is_err());
+        assert!(monitor(&["--tls-cert", "cert.pem", "--tls-key", "key.pem"]).is_ok());
+    }
+
+    #[test]
+    fn flags_belong_to_their_subcommand() {
+        assert!(Cli::try_parse_from(["qtop-verifier", "check", "1", "--interval", "5"]).is_err());
+        assert!(Cli::try_parse_from(["qtop-verifier", "monitor", "--format", "json"]).is_err());
+    }
+}
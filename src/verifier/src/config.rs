@@ -0,0 +1,265 @@
+use crate::cli::{self, Command, MonitorArgs, OutputFormat};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Config file picked up from the working directory when `--config` is not
+/// given.
+pub const DEFAULT_CONFIG_PATH: &str = "qtop.toml";
+
+/// Settings read from a `qtop.toml` file. Every field is optional: flags
+/// given on the command line or through their `QTOP_*` environment
+/// variables take precedence over these, which in turn take precedence over
+/// the built-in defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip)]
+    path: PathBuf,
+    pub bind: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub interval: Option<DurationSetting>,
+    pub concurrency: Option<NonZeroUsize>,
+    pub format: Option<OutputFormat>,
+    /// Circuit definitions file, relative to the config file.
+    pub circuits: Option<PathBuf>,
+    /// Secret `shutdown --confirm` must be given.
+    pub shutdown_token: Option<String>,
+}
+
+/// A duration written either as a number of seconds or as a humantime
+/// string such as `"5m"`. It is only validated when actually used, so a
+/// command-line flag can stand in for a bad value.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DurationSetting {
+    Seconds(f64),
+    Text(String),
+}
+
+impl Config {
+    /// Load the config file at `path`, or `./qtop.toml` if no path is given
+    /// and that file exists.
+    pub fn discover(path: Option<&Path>) -> Result<Option<Self>> {
+        match path {
+            Some(path) => Self::load(path).map(Some),
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => {
+                Self::load(Path::new(DEFAULT_CONFIG_PATH)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        Self::parse(&contents, path)
+    }
+
+    fn parse(contents: &str, path: &Path) -> Result<Self> {
+        let mut config: Config = toml::from_str(contents)
+            .with_context(|| format!("failed to parse config file {}", path.display()))?;
+        config.path = path.to_path_buf();
+        Ok(config)
+    }
+
+    /// Fill in every setting `command` left unset from this file.
+    pub fn apply(&self, command: &mut Command) -> Result<()> {
+        match command {
+            Command::Monitor(args) => {
+                args.bind = args.bind.or(self.bind);
+                args.port = args.port.or(self.port);
+                if args.interval.is_none() {
+                    args.interval = self.interval()?;
+                }
+                args.circuits = args.circuits.take().or_else(|| self.circuits());
+            }
+            Command::Check(args) => {
+                args.circuits = args.circuits.take().or_else(|| self.circuits());
+                args.format = args.format.or(self.format);
+                args.concurrency = args.concurrency.or(self.concurrency);
+            }
+            Command::Shutdown(args) => {
+                args.host = args
+                    .host
+                    .take()
+                    .or_else(|| self.bind.map(|ip| ip.to_string()));
+                args.port = args.port.or(self.port);
+                args.shutdown_token = self.shutdown_token.clone();
+            }
+            Command::History(args) => args.format = args.format.or(self.format),
+            Command::Diff(args) => {
+                args.circuits = args.circuits.take().or_else(|| self.circuits());
+                args.format = args.format.or(self.format);
+            }
+            Command::Bench(args) => args.format = args.format.or(self.format),
+            Command::List(args) => {
+                args.circuits = args.circuits.take().or_else(|| self.circuits());
+                args.format = args.format.or(self.format);
+            }
+            Command::VerifyProof(_) | Command::Completions(_) | Command::Selftest => {}
+        }
+        Ok(())
+    }
+
+    fn interval(&self) -> Result<Option<Duration>> {
+        let Some(setting) = &self.interval else {
+            return Ok(None);
+        };
+        let parsed = match setting {
+            DurationSetting::Seconds(secs) => cli::parse_duration(&secs.to_string()),
+            DurationSetting::Text(text) => cli::parse_duration(text),
+        };
+        parsed.map(Some).map_err(|e| {
+            anyhow!(
+                "invalid interval in config file {}: {} (pass --interval to override it; \
+                 command-line flags take precedence over the config file, which takes \
+                 precedence over built-in defaults)",
+                self.path.display(),
+                e
+            )
+        })
+    }
+
+    fn circuits(&self) -> Option<PathBuf> {
+        let dir = self.path.parent().unwrap_or(Path::new(""));
+        self.circuits.as_ref().map(|path| dir.join(path))
+    }
+}
+
+/// Derives the monitor's settings from its command-line flags and the
+/// config file, re-reading the file each time so that a running monitor
+/// can pick up changes.
+pub struct MonitorSettings {
+    flags: MonitorArgs,
+    path: Option<PathBuf>,
+}
+
+impl MonitorSettings {
+    pub fn new(flags: MonitorArgs, path: Option<PathBuf>) -> Self {
+        Self { flags, path }
+    }
+
+    pub fn load(&self) -> Result<MonitorArgs> {
+        let mut command = Command::Monitor(self.flags.clone());
+        if let Some(config) = Config::discover(self.path.as_deref())? {
+            config.apply(&mut command)?;
+        }
+        let Command::Monitor(args) = command else {
+            unreachable!("applying a config file keeps the command")
+        };
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    fn config(contents: &str) -> Config {
+        Config::parse(contents, Path::new("/etc/qtop/qtop.toml")).unwrap()
+    }
+
+    fn command(args: &[&str]) -> Command {
+        let argv = std::iter::once("qtop-verifier").chain(args.iter().copied());
+        Cli::try_parse_from(argv).unwrap().command
+    }
+
+    #[test]
+    fn file_values_fill_unset_flags() {
+        let config = config(
+            r#"
+            bind = "::1"
+            port = 9191
+            interval = "5m"
+            circuits = "circuits.toml"
+            "#,
+        );
+        let mut monitor = command(&["monitor"]);
+        config.apply(&mut monitor).unwrap();
+        let Command::Monitor(args) = monitor else {
+            unreachable!()
+        };
+        assert_eq!(args.bind(), "::1".parse::<IpAddr>().unwrap());
+        assert_eq!(args.port(), 9191);
+        assert_eq!(args.interval(), Duration::from_secs(300));
+        assert_eq!(
+            args.circuits.as_deref(),
+            Some(Path::new("/etc/qtop/circuits.toml"))
+        );
+    }
+
+    #[test]
+    fn flags_override_file_values() {
+        let config = config(
+            r#"
+            format = "json"
+            concurrency = 8
+            circuits = "circuits.toml"
+            "#,
+        );
+        let mut check = command(&[
+            "check",
+            "1",
+            "--format",
+            "text",
+            "--concurrency",
+            "2",
+            "--circuits",
+            "local.toml",
+        ]);
+        config.apply(&mut check).unwrap();
+        let Command::Check(args) = check else {
+            unreachable!()
+        };
+        assert_eq!(args.format(), OutputFormat::Text);
+        assert_eq!(args.concurrency, NonZeroUsize::new(2));
+        assert_eq!(args.circuits.as_deref(), Some(Path::new("local.toml")));
+    }
+
+    #[test]
+    fn defaults_apply_when_neither_is_set() {
+        let mut shutdown = command(&["shutdown", "7"]);
+        Config::default().apply(&mut shutdown).unwrap();
+        let Command::Shutdown(args) = shutdown else {
+            unreachable!()
+        };
+        assert_eq!(args.port(), cli::DEFAULT_PORT);
+
+        let mut shutdown = command(&["shutdown", "7"]);
+        config("bind = \"::1\"").apply(&mut shutdown).unwrap();
+        let Command::Shutdown(args) = shutdown else {
+            unreachable!()
+        };
+        assert_eq!(args.authority(), "[::1]:9090");
+
+        let mut monitor = command(&["monitor"]);
+        Config::default().apply(&mut monitor).unwrap();
+        let Command::Monitor(args) = monitor else {
+            unreachable!()
+        };
+        assert_eq!(args.bind(), cli::DEFAULT_BIND);
+    }
+
+    #[test]
+    fn invalid_interval_is_only_an_error_without_the_flag() {
+        let config = config("interval = 0");
+
+        let mut overridden = command(&["monitor", "--interval", "2"]);
+        config.apply(&mut overridden).unwrap();
+
+        let err = config.apply(&mut command(&["monitor"])).unwrap_err();
+        assert!(err.to_string().contains("pass --interval to override"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = Config::parse("prot = 9090", Path::new("qtop.toml")).unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown field"));
+    }
+}
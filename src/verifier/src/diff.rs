@@ -0,0 +1,109 @@
+use crate::cli::OutputFormat;
+use crate::output::Output;
+use crate::ui::Glyph;
+use anyhow::Result;
+use qtop_core::Circuit;
+use serde::Serialize;
+use std::io::Write;
+
+/// Winding numbers of two circuits, compared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindingDiff {
+    pub circuit_a: u64,
+    pub circuit_b: u64,
+    pub winding_a: i64,
+    pub winding_b: i64,
+    /// `winding_b - winding_a`.
+    pub delta: i64,
+    pub equal: bool,
+}
+
+/// Compare the windings of `a` and `b`, failing if either has too few
+/// phases to form a loop rather than reporting a winding of 0 for it.
+pub fn compare(a: &Circuit, b: &Circuit) -> qtop_core::Result<WindingDiff> {
+    a.ensure_phase_loop()?;
+    b.ensure_phase_loop()?;
+    let winding_a = a.winding_number();
+    let winding_b = b.winding_number();
+    Ok(WindingDiff {
+        circuit_a: a.id,
+        circuit_b: b.id,
+        winding_a,
+        winding_b,
+        delta: winding_b - winding_a,
+        equal: winding_a == winding_b,
+    })
+}
+
+pub fn report(diff: &WindingDiff, format: OutputFormat, out: &mut Output) -> Result<()> {
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string(diff)?)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            writer.serialize(diff)?;
+            writer.flush()?;
+        }
+        OutputFormat::Text if diff.equal => writeln!(
+            out,
+            "{} Circuits {} and {} both have winding number {}",
+            Glyph::Ok,
+            diff.circuit_a,
+            diff.circuit_b,
+            diff.winding_a
+        )?,
+        OutputFormat::Text => writeln!(
+            out,
+            "{} Circuits {} and {} differ: winding number {} vs {} (delta {:+})",
+            Glyph::Fail,
+            diff.circuit_a,
+            diff.circuit_b,
+            diff.winding_a,
+            diff.winding_b,
+            diff.delta
+        )?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qtop_core::QtopError;
+    use std::f64::consts::TAU;
+
+    fn loop_circuit(id: u64, samples: usize, turns: f64) -> Circuit {
+        let phases = (0..samples)
+            .map(|i| turns * TAU * i as f64 / samples as f64)
+            .collect();
+        Circuit::new(id, phases)
+    }
+
+    #[test]
+    fn resampled_loop_matches_original() {
+        let diff = compare(&loop_circuit(1, 8, 1.0), &loop_circuit(2, 64, 1.0)).unwrap();
+        assert!(diff.equal);
+        assert_eq!(diff.delta, 0);
+    }
+
+    #[test]
+    fn delta_is_candidate_minus_golden() {
+        let diff = compare(&loop_circuit(1, 8, 1.0), &loop_circuit(2, 16, -2.0)).unwrap();
+        assert!(!diff.equal);
+        assert_eq!((diff.winding_a, diff.winding_b, diff.delta), (1, -2, -3));
+    }
+
+    #[test]
+    fn circuits_without_a_phase_loop_are_rejected() {
+        let empty = Circuit::new(3, vec![]);
+        let single = Circuit::new(5, vec![1.0]);
+        assert!(matches!(
+            compare(&empty, &single),
+            Err(QtopError::TooFewPhases { circuit_id: 3, .. })
+        ));
+        assert!(matches!(
+            compare(&loop_circuit(1, 8, 1.0), &single),
+            Err(QtopError::TooFewPhases { circuit_id: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::check::CheckOutcome;
+use crate::monitor;
+use crate::registry::CircuitStatus;
+use crate::server::{self, Limits, MonitorState};
+use axum_server::tls_rustls::RustlsConfig;
+use qtop_core::{Invariant, VerificationResult};
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::service::Routes;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+pub mod proto {
+    tonic::include_proto!("qtop.v1");
+}
+
+use proto::verifier_server::{Verifier, VerifierServer};
+
+/// The gRPC counterpart of the HTTP endpoints, answering from the same
+/// [`MonitorState`] so both can run side by side.
+pub struct VerifierService {
+    state: MonitorState,
+    timeout: Option<Duration>,
+}
+
+impl VerifierService {
+    pub fn new(state: MonitorState, timeout: Option<Duration>) -> Self {
+        Self { state, timeout }
+    }
+}
+
+#[tonic::async_trait]
+impl Verifier for VerifierService {
+    async fn check_circuit(
+        &self,
+        request: Request<proto::CheckCircuitRequest>,
+    ) -> Result<Response<proto::CheckCircuitResponse>, Status> {
+        let id = request.into_inner().circuit_id;
+        let circuit = self
+            .state
+            .registry
+            .circuit(id)
+            .await
+            .ok_or_else(|| not_found(id))?;
+        let outcome = monitor::verify_circuit(&self.state, &circuit, self.timeout)
+            .await
+            .ok_or_else(|| not_found(id))?;
+        match outcome {
+            CheckOutcome::Verified(result) | CheckOutcome::CrossChecked { result, .. } => {
+                Ok(Response::new(proto::CheckCircuitResponse {
+                    result: Some(result.into()),
+                }))
+            }
+            CheckOutcome::Failed { error, .. } => Err(Status::internal(error)),
+            CheckOutcome::TimedOut {
+                timed_out_after_secs,
+                ..
+            } => Err(Status::deadline_exceeded(format!(
+                "verification timed out after {}s",
+                timed_out_after_secs
+            ))),
+        }
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<proto::GetStatusRequest>,
+    ) -> Result<Response<proto::GetStatusResponse>, Status> {
+        let circuits = self.state.registry.statuses().await;
+        Ok(Response::new(proto::GetStatusResponse {
+            circuits: circuits.into_iter().map(Into::into).collect(),
+            cache: self.state.cache.as_ref().map(|cache| {
+                let stats = cache.stats();
+                proto::CacheStats {
+                    hits: stats.hits,
+                    misses: stats.misses,
+                    entries: stats.entries as u64,
+                }
+            }),
+        }))
+    }
+
+    async fn shutdown(
+        &self,
+        request: Request<proto::ShutdownRequest>,
+    ) -> Result<Response<proto::ShutdownResponse>, Status> {
+        let id = request.into_inner().circuit_id;
+        let timeout = self.state.drain_timeout;
+        let drained = self
+            .state
+            .registry
+            .drain(id, timeout)
+            .await
+            .ok_or_else(|| not_found(id))?;
+        Ok(Response::new(proto::ShutdownResponse {
+            circuit_id: id,
+            drained: server::log_shutdown(id, drained, timeout),
+        }))
+    }
+}
+
+fn not_found(id: u64) -> Status {
+    Status::not_found(format!("circuit {} not found", id))
+}
+
+impl From<Invariant> for proto::Invariant {
+    fn from(invariant: Invariant) -> Self {
+        match invariant {
+            Invariant::Winding => proto::Invariant::Winding,
+            Invariant::Chern => proto::Invariant::Chern,
+        }
+    }
+}
+
+impl From<VerificationResult> for proto::VerificationResult {
+    fn from(result: VerificationResult) -> Self {
+        Self {
+            circuit_id: result.circuit_id,
+            invariant: proto::Invariant::from(result.invariant).into(),
+            winding_number: result.winding_number,
+            expected_winding: result.expected_winding,
+            valid: result.valid,
+            confidence: result.confidence,
+        }
+    }
+}
+
+impl From<CircuitStatus> for proto::CircuitStatus {
+    fn from(status: CircuitStatus) -> Self {
+        Self {
+            circuit_id: status.circuit_id,
+            last_result: status.last_result.map(Into::into),
+            last_checked: status.last_checked.map(|at| at.to_rfc3339()),
+            latency: status.latency.map(|latency| proto::LatencySummary {
+                samples: latency.samples as u64,
+                min_secs: latency.min_secs,
+                max_secs: latency.max_secs,
+                mean_secs: latency.mean_secs,
+                p95_secs: latency.p95_secs,
+            }),
+            draining: status.draining,
+        }
+    }
+}
+
+/// Serve the gRPC interface on `listener` until `shutdown` resolves, with
+/// the same `limits` and TLS as the HTTP endpoints, so that `Shutdown` is
+/// never reachable more openly than `/shutdown`.
+pub async fn serve(
+    listener: TcpListener,
+    service: VerifierService,
+    limits: Limits,
+    tls: Option<RustlsConfig>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    info!(addr = ?listener.local_addr().ok(), tls = tls.is_some(), "gRPC server started");
+    let app = Routes::new(VerifierServer::new(service)).into_axum_router();
+    server::serve_app(listener, app, limits, tls, shutdown).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::verifier_client::VerifierClient;
+    use super::*;
+    use crate::metrics::Metrics;
+    use qtop_core::Circuit;
+    use std::f64::consts::TAU;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn rpcs_share_state_with_the_registry() {
+        let phases = (0..4).map(|i| TAU * i as f64 / 4.0).collect();
+        let state = MonitorState::new(
+            &[Circuit::new(1, phases).with_expected_winding(1)],
+            Metrics::new().unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let limits = Limits {
+            request_timeout: Duration::from_secs(30),
+            max_connections: 8,
+        };
+        let server = tokio::spawn(serve(
+            listener,
+            VerifierService::new(state.clone(), None),
+            limits,
+            None,
+            async {
+                let _ = stopped.await;
+            },
+        ));
+
+        let mut client = VerifierClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let result = client
+            .check_circuit(proto::CheckCircuitRequest { circuit_id: 1 })
+            .await
+            .unwrap()
+            .into_inner()
+            .result
+            .unwrap();
+        assert_eq!(result.winding_number, 1);
+        assert!(result.valid);
+        assert!(result.confidence.is_some());
+        // The check was recorded where the HTTP server reads it from.
+        assert!(state
+            .registry
+            .status(1)
+            .await
+            .unwrap()
+            .last_result
+            .is_some());
+
+        let status = client
+            .get_status(proto::GetStatusRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.circuits.len(), 1);
+        assert!(status.circuits[0].latency.is_some());
+
+        let shutdown = client
+            .shutdown(proto::ShutdownRequest { circuit_id: 1 })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(shutdown.drained);
+        assert!(state.registry.circuits().await.is_empty());
+        let missing = client
+            .check_circuit(proto::CheckCircuitRequest { circuit_id: 1 })
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use qtop_core::VerificationResult;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how
+/// many have run, so new entries must only ever be appended.
+const MIGRATIONS: &[&str] = &["CREATE TABLE verifications (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        circuit_id INTEGER NOT NULL,
+        winding_number INTEGER NOT NULL,
+        valid INTEGER NOT NULL,
+        checked_at TEXT NOT NULL
+    );
+    CREATE INDEX verifications_circuit_id ON verifications (circuit_id, checked_at);"];
+
+/// A stored verification of one circuit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub circuit_id: u64,
+    pub winding_number: i64,
+    pub valid: bool,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Audit trail of verification results kept in SQLite.
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open (creating if needed) the database at `path` and bring its schema
+    /// up to date.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open history database {}", path.display()))?;
+        Self::from_connection(conn)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self> {
+        let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let tx = conn.transaction()?;
+        for migration in MIGRATIONS.iter().skip(applied) {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        tx.commit()?;
+        Ok(Self { conn })
+    }
+
+    pub fn record(&self, result: &VerificationResult, checked_at: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO verifications (circuit_id, winding_number, valid, checked_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                result.circuit_id as i64,
+                result.winding_number,
+                result.valid,
+                // Fixed-width so timestamps sort lexically.
+                checked_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// All recorded verifications of `circuit_id`, oldest first.
+    pub fn for_circuit(&self, circuit_id: u64) -> Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT winding_number, valid, checked_at FROM verifications
+             WHERE circuit_id = ?1 ORDER BY checked_at, id",
+        )?;
+        let rows = statement.query_map([circuit_id as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        rows.map(|row| {
+            let (winding_number, valid, checked_at) = row?;
+            Ok(HistoryEntry {
+                circuit_id,
+                winding_number,
+                valid,
+                checked_at: DateTime::parse_from_rfc3339(&checked_at)?.with_timezone(&Utc),
+            })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qtop_core::Invariant;
+
+    fn result(circuit_id: u64, winding_number: i64) -> VerificationResult {
+        VerificationResult {
+            circuit_id,
+            invariant: Invariant::Winding,
+            winding_number,
+            expected_winding: None,
+            confidence: None,
+            valid: winding_number != 0,
+        }
+    }
+
+    #[test]
+    fn records_are_returned_per_circuit_in_order() {
+        let history = History::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let earlier = Utc::now() - chrono::Duration::minutes(5);
+        let later = Utc::now();
+
+        history.record(&result(1, 0), later).unwrap();
+        history.record(&result(2, 1), later).unwrap();
+        history.record(&result(1, 1), earlier).unwrap();
+
+        let entries = history.for_circuit(1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].winding_number, 1);
+        assert!(entries[0].valid);
+        assert_eq!(entries[1].winding_number, 0);
+        assert!(!entries[1].valid);
+        assert!(history.for_circuit(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let history = History::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        history.record(&result(7, 1), Utc::now()).unwrap();
+
+        let reopened = History::from_connection(history.conn).unwrap();
+        assert_eq!(reopened.for_circuit(7).unwrap().len(), 1);
+    }
+}
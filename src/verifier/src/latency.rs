@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of recent verifications latency statistics are computed over.
+const WINDOW: usize = 128;
+
+/// Durations of a circuit's most recent verifications.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyWindow {
+    samples: VecDeque<Duration>,
+}
+
+/// Latency statistics over a circuit's recent verifications, in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub min_secs: f64,
+    pub max_secs: f64,
+    pub mean_secs: f64,
+    pub p95_secs: f64,
+}
+
+impl LatencyWindow {
+    pub fn push(&mut self, duration: Duration) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let n = sorted.len();
+        let total: Duration = sorted.iter().sum();
+        // Nearest-rank percentile.
+        let p95 = sorted[(n * 95).div_ceil(100) - 1];
+        Some(LatencySummary {
+            samples: n,
+            min_secs: sorted[0].as_secs_f64(),
+            max_secs: sorted[n - 1].as_secs_f64(),
+            mean_secs: total.as_secs_f64() / n as f64,
+            p95_secs: p95.as_secs_f64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_window_has_no_summary() {
+        assert_eq!(LatencyWindow::default().summary(), None);
+    }
+
+    #[test]
+    fn summary_over_samples() {
+        let mut window = LatencyWindow::default();
+        for ms in (1..=100).rev() {
+            window.push(Duration::from_millis(ms));
+        }
+
+        let summary = window.summary().unwrap();
+        assert_eq!(summary.samples, 100);
+        assert_eq!(summary.min_secs, 0.001);
+        assert_eq!(summary.max_secs, 0.1);
+        assert!((summary.mean_secs - 0.0505).abs() < 1e-9);
+        assert_eq!(summary.p95_secs, 0.095);
+    }
+
+    #[test]
+    fn only_recent_samples_are_kept() {
+        let mut window = LatencyWindow::default();
+        window.push(Duration::from_secs(60));
+        for _ in 0..WINDOW {
+            window.push(Duration::from_millis(1));
+        }
+
+        let summary = window.summary().unwrap();
+        assert_eq!(summary.samples, WINDOW);
+        assert_eq!(summary.max_secs, 0.001);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::cli::OutputFormat;
+use crate::output::Output;
+use anyhow::Result;
+use qtop_core::Circuit;
+use serde::Serialize;
+use std::io::Write;
+
+/// What `list` shows about one circuit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitSummary {
+    pub circuit_id: u64,
+    pub phase_samples: usize,
+    pub expected_winding: Option<i64>,
+}
+
+impl From<&Circuit> for CircuitSummary {
+    fn from(circuit: &Circuit) -> Self {
+        Self {
+            circuit_id: circuit.id,
+            phase_samples: circuit.phases.len(),
+            expected_winding: circuit.expected_winding,
+        }
+    }
+}
+
+pub fn report(circuits: &[Circuit], format: OutputFormat, out: &mut Output) -> Result<()> {
+    let summaries = circuits.iter().map(CircuitSummary::from);
+    match format {
+        OutputFormat::Json => {
+            for summary in summaries {
+                writeln!(out, "{}", serde_json::to_string(&summary)?)?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut *out);
+            for summary in summaries {
+                writer.serialize(summary)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Text => {
+            writeln!(out, "{:>10}  {:>8}  EXPECTED", "ID", "SAMPLES")?;
+            for summary in summaries {
+                let expected = summary
+                    .expected_winding
+                    .map_or_else(|| "-".to_string(), |w| w.to_string());
+                writeln!(
+                    out,
+                    "{:>10}  {:>8}  {}",
+                    summary.circuit_id, summary.phase_samples, expected
+                )?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
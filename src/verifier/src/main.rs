@@ -1,77 +1,671 @@
-use anyhow::Result;
-use clap::Parser;
-use std::time::Duration;
-use tokio::time::sleep;
-
-#[derive(Parser)]
-#[command(name = "qtop-verifier")]
-#[command(about = "Quantum topological winding number verifier", long_about = None)]
-struct Args {
-    #[arg(short, long, default_value = "9090")]
-    port: u16,
-    
-    #[arg(short, long)]
-    monitor: bool,
-    
-    #[arg(long)]
-    check_circuit: Option<u64>,
-    
-    #[arg(long)]
-    emergency_shutdown: Option<u64>,
+use anyhow::{Context, Result};
+use cli::{
+    BenchArgs, CheckArgs, CircuitSelector, Cli, Command, CompletionsArgs, DiffArgs, HistoryArgs,
+    InputKind, ListArgs, LogFormat, OutputFormat, ShutdownArgs, VerifyProofArgs,
+};
+use qtop_core::{Circuit, Invariant, VerificationResult};
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use ui::Glyph;
+
+mod alert;
+mod audit;
+mod bench;
+mod cache;
+mod check;
+mod cli;
+mod config;
+mod diff;
+mod grpc;
+mod history;
+mod latency;
+mod list;
+mod metrics;
+mod monitor;
+mod output;
+mod proof;
+mod quorum;
+mod registry;
+mod retry;
+mod schedule;
+mod selftest;
+mod server;
+mod shutdown;
+mod ui;
+mod watch;
+
+/// Overall result of a run, mapped onto the process exit code so scripts
+/// and CI gates can tell a winding violation apart from the verifier itself
+/// failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Success,
+    Violation,
+    Error,
+}
+
+impl Outcome {
+    fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::Error => 1,
+            Outcome::Violation => 2,
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-    
-    println!("🔍 Quantum Topological Winding Number Verifier v0.1.0");
-    println!("⚛️  Starting verifier on port {}", args.port);
-    
-    if args.monitor {
-        println!("📊 Starting monitoring mode...");
-        start_monitoring(args.port).await?;
-    }
-    
-    if let Some(circuit_id) = args.check_circuit {
-        println!("🔍 Checking circuit {}...", circuit_id);
-        check_circuit(circuit_id).await?;
-    }
-    
-    if let Some(circuit_id) = args.emergency_shutdown {
-        println!("🚨 Emergency shutdown for circuit {}...", circuit_id);
-        emergency_shutdown(circuit_id).await?;
-    }
-    
-    Ok(())
+async fn main() {
+    // clap exits with 2 on usage errors, which would read as a violation.
+    let cli = cli::try_parse_from(std::env::args_os()).unwrap_or_else(|e| {
+        let _ = e.print();
+        let outcome = if e.use_stderr() {
+            Outcome::Error
+        } else {
+            Outcome::Success
+        };
+        std::process::exit(outcome.exit_code());
+    });
+
+    ui::use_plain_markers(cli.no_emoji);
+    init_tracing(cli.log_format);
+
+    let code = match run(cli).await {
+        Ok(outcome) => outcome.exit_code(),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            Outcome::Error.exit_code()
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Operational logs go to stderr, filtered by `RUST_LOG` (default `info`),
+/// leaving stdout for user-facing results.
+fn init_tracing(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => subscriber.with_ansi(ui::color_enabled()).init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
 }
 
-async fn start_monitoring(port: u16) -> Result<()> {
-    println!("📊 Monitoring started on port {}", port);
-    
+async fn run(cli: Cli) -> Result<Outcome> {
+    let mut command = cli.command;
+    // The monitor re-reads the config file itself when asked to reload.
+    if let Command::Monitor(flags) = command {
+        return run_monitor(config::MonitorSettings::new(flags, cli.config)).await;
+    }
+    // Completions must work even where the config file doesn't load.
+    if let Command::Completions(args) = command {
+        return run_completions(args);
+    }
+    // As should the self-test, which only checks the binary itself.
+    if let Command::Selftest = command {
+        return Ok(run_selftest());
+    }
+    if let Some(config) = config::Config::discover(cli.config.as_deref())? {
+        config.apply(&mut command)?;
+    }
+
+    match command {
+        Command::Monitor(_) => unreachable!("handled above"),
+        Command::Check(args) => run_check(args).await,
+        Command::Shutdown(args) => run_shutdown(args).await,
+        Command::History(args) => run_history(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Bench(args) => run_bench(args),
+        Command::List(args) => run_list(args),
+        Command::VerifyProof(args) => run_verify_proof(args),
+        Command::Completions(_) | Command::Selftest => unreachable!("handled above"),
+    }
+}
+
+fn load_circuits(path: Option<&Path>) -> Result<Vec<Circuit>> {
+    match path {
+        Some(path) => qtop_core::load_circuits(path)
+            .with_context(|| format!("failed to load circuits from {}", path.display())),
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn run_monitor(settings: config::MonitorSettings) -> Result<Outcome> {
+    let args = settings.load()?;
+    // With --stream, stdout carries nothing but the results.
+    output::progress(
+        args.stream,
+        format_args!(
+            "{} Quantum Topological Winding Number Verifier v0.1.0",
+            Glyph::Check
+        ),
+    );
+    output::progress(
+        args.stream,
+        format_args!(
+            "{} Starting verifier on {}",
+            Glyph::Info,
+            std::net::SocketAddr::new(args.bind(), args.port())
+        ),
+    );
+
+    let circuits = load_circuits(args.circuits.as_deref())?;
+    info!("starting monitoring mode");
+    monitor::start_monitoring(args, circuits, &settings).await?;
+    Ok(Outcome::Success)
+}
+
+async fn run_check(args: CheckArgs) -> Result<Outcome> {
+    if args.format() == OutputFormat::Text {
+        output::progress(
+            args.output.is_some(),
+            format_args!(
+                "{} Quantum Topological Winding Number Verifier v0.1.0",
+                Glyph::Check
+            ),
+        );
+    }
+
+    let history = args.db.as_deref().map(history::History::open).transpose()?;
+    // Kept across --watch passes, where unchanged circuits are re-checked.
+    let cache = (!args.no_cache).then(Arc::default);
+    if args.watch {
+        watch_check(&args, history.as_ref(), cache.as_ref()).await
+    } else {
+        check_once(&args, history.as_ref(), cache.as_ref()).await
+    }
+}
+
+/// Re-run the check every time the circuits file changes, until
+/// interrupted. A pass that fails, e.g. on a half-written file, is reported
+/// and the watch carries on.
+async fn watch_check(
+    args: &CheckArgs,
+    history: Option<&history::History>,
+    cache: Option<&Arc<cache::WindingCache>>,
+) -> Result<Outcome> {
+    let Some(path) = args.circuits.as_deref() else {
+        anyhow::bail!("--watch requires --circuits <PATH>");
+    };
+    if args.ids.contains(&CircuitSelector::Stdin) {
+        anyhow::bail!("--watch cannot re-read phase data from stdin");
+    }
+    let text = args.format() == OutputFormat::Text;
+    let mut watcher = watch::FileWatcher::new(path)?;
+    let shutdown = monitor::shutdown_signal()?;
+    tokio::pin!(shutdown);
+
+    let mut outcome = report_pass(check_once(args, history, cache).await);
     loop {
-        // Simulate monitoring
-        println!("✅ All winding numbers verified");
-        sleep(Duration::from_secs(10)).await;
+        if text {
+            output::progress(
+                args.output.is_some(),
+                format_args!(
+                    "{} Watching {} for changes...",
+                    Glyph::Watch,
+                    path.display()
+                ),
+            );
+        }
+        tokio::select! {
+            _ = &mut shutdown => break,
+            changed = watcher.changed() => changed?,
+        }
+        if text {
+            output::progress(args.output.is_some(), format_args!(""));
+        }
+        outcome = report_pass(check_once(args, history, cache).await);
+    }
+    Ok(outcome)
+}
+
+fn report_pass(result: Result<Outcome>) -> Outcome {
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {:#}", e);
+        Outcome::Error
+    })
+}
+
+async fn check_once(
+    args: &CheckArgs,
+    history: Option<&history::History>,
+    cache: Option<&Arc<cache::WindingCache>>,
+) -> Result<Outcome> {
+    if args.range.is_some() && args.invariant == Invariant::Chern {
+        anyhow::bail!("--range selects phase samples, so it only applies to --invariant winding");
+    }
+    let text = args.format() == OutputFormat::Text;
+    let redirected = args.output.is_some();
+    let mut out = output::Output::open(args.output.as_deref())?;
+    let (circuits, unloadable) = match &args.circuits_dir {
+        Some(dir) => load_circuit_dir(dir, args.format(), &mut out)?,
+        None => (load_circuits(args.circuits.as_deref())?, 0),
+    };
+
+    let mut batch = Vec::with_capacity(args.ids.len());
+    if args.ids.is_empty() {
+        for circuit in &circuits {
+            batch.push(prepare(circuit.clone(), args)?);
+        }
+    }
+    for selector in &args.ids {
+        let circuit = match *selector {
+            CircuitSelector::Id(_) if args.circuits.is_none() && args.circuits_dir.is_none() => {
+                anyhow::bail!("checking circuits by id requires --circuits <PATH>")
+            }
+            CircuitSelector::Id(id) => qtop_core::find_circuit(&circuits, id)?.clone(),
+            CircuitSelector::Stdin if args.input_kind == InputKind::Braiding => {
+                anyhow::bail!("braiding matrices cannot be read from stdin")
+            }
+            CircuitSelector::Stdin => read_stdin_circuit().await?,
+        };
+        batch.push(prepare(circuit, args)?);
+    }
+    if args.invariant == Invariant::Chern {
+        if let Some(circuit) = batch.iter().find(|c| c.berry_flux.is_empty()) {
+            anyhow::bail!(
+                "circuit {} has no berry_flux to compute a Chern number from",
+                circuit.id
+            );
+        }
+    }
+    if text {
+        output::progress(
+            redirected,
+            format_args!("{} Checking {} circuit(s)...", Glyph::Check, batch.len()),
+        );
+    }
+
+    let concurrency = args
+        .concurrency
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let options = check::CheckOptions {
+        concurrency,
+        invariant: args.invariant,
+        expected: args.expected,
+        tolerance: args.tolerance,
+        min_confidence: Some(args.min_confidence),
+        timeout: args.timeout,
+        format: args.format(),
+        cache: cache.cloned(),
+        fail_fast: args.fail_fast,
+        // Only where the results themselves are going to a terminal too,
+        // or to a file, so piped output is never interleaved with a bar.
+        progress_bar: text
+            && std::io::stderr().is_terminal()
+            && (redirected || std::io::stdout().is_terminal()),
+    };
+    let inputs = args.proof.as_ref().map(|_| batch.clone());
+    let peers = match &args.peers {
+        Some(peers) => Some(quorum::PeerCheck::new(peers.clone(), args.quorum)?),
+        None => None,
+    };
+    let total = batch.len();
+    let outcomes = check::check_batch(batch, options, peers.as_ref(), &mut out).await?;
+    if outcomes.len() < total {
+        let tripped = outcomes.last().map_or(0, |outcome| outcome.circuit_id());
+        warn!(
+            circuit_id = tripped,
+            skipped = total - outcomes.len(),
+            "stopped at the first failed circuit (--fail-fast)"
+        );
+        if text {
+            output::progress(
+                redirected,
+                format_args!(
+                    "{} Stopped at circuit {} (--fail-fast); {} circuit(s) not checked",
+                    Glyph::Stop,
+                    tripped,
+                    total - outcomes.len()
+                ),
+            );
+        }
+    }
+    if let (Some(path), Some(inputs)) = (&args.proof, inputs) {
+        let proofs = inputs
+            .iter()
+            .zip(&outcomes)
+            .filter_map(|(circuit, outcome)| match outcome {
+                check::CheckOutcome::Verified(result) => {
+                    Some(proof::Proof::new(circuit, result, args.tolerance))
+                }
+                // The proof covers the local computation, which is what
+                // verify-proof can re-run.
+                check::CheckOutcome::CrossChecked {
+                    result,
+                    locally_valid,
+                    ..
+                } => {
+                    let local = VerificationResult {
+                        valid: *locally_valid,
+                        ..result.clone()
+                    };
+                    Some(proof::Proof::new(circuit, &local, args.tolerance))
+                }
+                _ => None,
+            })
+            .collect::<Result<Vec<_>>>()?;
+        proof::ProofFile::new(proofs).write(path)?;
+    }
+    if let Some(history) = history {
+        let checked_at = chrono::Utc::now();
+        for outcome in &outcomes {
+            if let Some(result) = outcome.result() {
+                history.record(result, checked_at)?;
+            }
+        }
+    }
+
+    let failed = outcomes.iter().filter(|o| !o.is_valid()).count();
+    if text {
+        let unloaded = match unloadable {
+            0 => String::new(),
+            n => format!(", {} file(s) could not be loaded", n),
+        };
+        output::progress(
+            redirected,
+            format_args!(
+                "{} {} passed, {} failed{}",
+                Glyph::Summary,
+                outcomes.len() - failed,
+                failed,
+                unloaded
+            ),
+        );
+    }
+    if unloadable > 0 || outcomes.iter().any(check::CheckOutcome::is_error) {
+        Ok(Outcome::Error)
+    } else if failed > 0 {
+        Ok(Outcome::Violation)
+    } else {
+        Ok(Outcome::Success)
     }
 }
 
-async fn check_circuit(circuit_id: u64) -> Result<()> {
-    println!("🔍 Checking winding number for circuit {}...", circuit_id);
-    
-    // Simulate verification
-    let is_valid = circuit_id % 2 == 0; // Simple test
-    
-    if is_valid {
-        println!("✅ Circuit {} winding number is valid", circuit_id);
+async fn run_shutdown(args: ShutdownArgs) -> Result<Outcome> {
+    println!(
+        "{} Emergency shutdown for circuit {}...",
+        Glyph::Alert,
+        args.id
+    );
+    let client = shutdown::client(&args)?;
+    if args.dry_run {
+        shutdown::dry_run(&args, &client).await?;
+        return Ok(Outcome::Success);
+    }
+
+    let mut audit = args
+        .audit_log
+        .as_deref()
+        .map(audit::AuditLog::open)
+        .transpose()?;
+    let result = match shutdown::confirm(&args) {
+        Ok(()) => {
+            shutdown::emergency_shutdown(&client, &args.base_url(), args.id, args.retry.policy())
+                .await
+        }
+        Err(refused) => Err(refused),
+    };
+    if let Some(audit) = &mut audit {
+        audit.record(&audit::ShutdownRecord::new(
+            args.id,
+            &args.base_url(),
+            &result,
+        ))?;
+    }
+    result?;
+    Ok(Outcome::Success)
+}
+
+fn run_history(args: HistoryArgs) -> Result<Outcome> {
+    let history = history::History::open(&args.db)?;
+    let entries = history.for_circuit(args.id)?;
+    let mut out = output::Output::open(args.output.as_deref())?;
+    print_history(&entries, args.id, args.format(), &mut out)?;
+    Ok(Outcome::Success)
+}
+
+/// Load every circuit file found under `dir`. Files that fail to load are
+/// reported one by one rather than aborting the sweep; returns the circuits
+/// that did load and how many files did not.
+fn load_circuit_dir(
+    dir: &Path,
+    format: OutputFormat,
+    out: &mut output::Output,
+) -> Result<(Vec<Circuit>, usize)> {
+    let files = qtop_core::find_circuit_files(dir)
+        .with_context(|| format!("failed to search {} for circuit files", dir.display()))?;
+
+    let mut circuits = Vec::new();
+    let mut unloadable = 0;
+    for file in files {
+        match qtop_core::load_circuits(&file) {
+            Ok(loaded) => circuits.extend(loaded),
+            Err(e) => {
+                unloadable += 1;
+                match format {
+                    OutputFormat::Json => writeln!(
+                        out,
+                        "{}",
+                        serde_json::json!({ "file": file, "error": e.to_string() })
+                    )?,
+                    OutputFormat::Text => writeln!(
+                        out,
+                        "{} Failed to load {}: {}",
+                        Glyph::Fail,
+                        file.display(),
+                        e
+                    )?,
+                    // Keep stdout to the CSV rows.
+                    OutputFormat::Csv => {
+                        eprintln!("Error: failed to load {}: {}", file.display(), e)
+                    }
+                }
+            }
+        }
+    }
+    Ok((circuits, unloadable))
+}
+
+fn run_diff(args: DiffArgs) -> Result<Outcome> {
+    let Some(path) = args.circuits.as_deref() else {
+        anyhow::bail!("comparing circuits requires --circuits <PATH>");
+    };
+    let circuits = load_circuits(Some(path))?;
+    let a = qtop_core::find_circuit(&circuits, args.circuit_a)?.clone();
+    let b = qtop_core::find_circuit(&circuits, args.circuit_b)?.clone();
+    let a = with_input_kind(a, args.input_kind)?;
+    let b = with_input_kind(b, args.input_kind)?;
+
+    let diff = diff::compare(&a, &b)?;
+    let mut out = output::Output::open(args.output.as_deref())?;
+    diff::report(&diff, args.format(), &mut out)?;
+    Ok(if diff.equal {
+        Outcome::Success
     } else {
-        println!("❌ Circuit {} winding number violation detected", circuit_id);
+        Outcome::Violation
+    })
+}
+
+fn run_bench(args: BenchArgs) -> Result<Outcome> {
+    if args.sizes.contains(&0) {
+        anyhow::bail!("benchmark sizes must be at least 1");
     }
-    
-    Ok(())
+    let mut out = output::Output::open(args.output.as_deref())?;
+    let redirected = args.output.is_some();
+    let text = args.format() == OutputFormat::Text;
+    let seed = args.seed.unwrap_or_else(rand::random);
+    if text {
+        output::progress(
+            redirected,
+            format_args!(
+                "{} Seed {} (pass --seed {} to reproduce)",
+                Glyph::Info,
+                seed,
+                seed
+            ),
+        );
+    }
+    let results: Vec<_> = args
+        .sizes
+        .iter()
+        .map(|&size| {
+            if text {
+                output::progress(
+                    redirected,
+                    format_args!(
+                        "{} Timing {} windings of {} phases...",
+                        Glyph::Info,
+                        args.iters,
+                        size
+                    ),
+                );
+            }
+            bench::measure(size, args.iters, seed)
+        })
+        .collect();
+    bench::report(&results, args.format(), &mut out)?;
+    Ok(Outcome::Success)
 }
 
-async fn emergency_shutdown(circuit_id: u64) -> Result<()> {
-    println!("🚨 Emergency shutdown triggered for circuit {}", circuit_id);
-    println!("✅ Circuit {} has been safely shut down", circuit_id);
+fn run_completions(args: CompletionsArgs) -> Result<Outcome> {
+    let mut command = cli::command();
+    let name = command.get_name().to_string();
+    // Generated into memory first: `generate` panics on a failed write.
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, name, &mut script);
+    std::io::stdout().write_all(&script)?;
+    Ok(Outcome::Success)
+}
+
+fn run_list(args: ListArgs) -> Result<Outcome> {
+    let mut out = output::Output::open(args.output.as_deref())?;
+    let (circuits, unloadable) = match (&args.circuits_dir, &args.circuits) {
+        (Some(dir), _) => load_circuit_dir(dir, args.format(), &mut out)?,
+        (None, Some(path)) => (load_circuits(Some(path))?, 0),
+        (None, None) => {
+            anyhow::bail!("listing circuits requires --circuits <PATH> or --circuits-dir <PATH>")
+        }
+    };
+    list::report(&circuits, args.format(), &mut out)?;
+    Ok(if unloadable > 0 {
+        Outcome::Error
+    } else {
+        Outcome::Success
+    })
+}
+
+fn run_selftest() -> Outcome {
+    println!(
+        "{} Verifying reference circuits of known winding",
+        Glyph::Check
+    );
+    if selftest::run() {
+        Outcome::Success
+    } else {
+        Outcome::Error
+    }
+}
+
+fn run_verify_proof(args: VerifyProofArgs) -> Result<Outcome> {
+    let file = proof::ProofFile::read(&args.path)?;
+    println!(
+        "{} Re-checking {} proof(s) from {}",
+        Glyph::Check,
+        file.proofs.len(),
+        args.path.display()
+    );
+    Ok(if proof::report(&file)? {
+        Outcome::Success
+    } else {
+        Outcome::Violation
+    })
+}
+
+/// `circuit` with the phases `check` should verify: taken from the
+/// requested input, then narrowed to the requested range.
+fn prepare(circuit: Circuit, args: &CheckArgs) -> Result<Circuit> {
+    let circuit = with_input_kind(circuit, args.input_kind)?;
+    match &args.range {
+        Some(range) => Ok(circuit.with_phase_window(range.clone())?),
+        None => Ok(circuit),
+    }
+}
+
+/// Put the circuit data selected by `kind` into `circuit.phases`, which is
+/// what the winding is computed from.
+fn with_input_kind(mut circuit: Circuit, kind: InputKind) -> Result<Circuit> {
+    if kind == InputKind::Braiding {
+        circuit.phases = circuit.braiding_phases()?;
+    }
+    Ok(circuit)
+}
+
+async fn read_stdin_circuit() -> Result<Circuit> {
+    let mut input = String::new();
+    tokio::io::stdin()
+        .read_to_string(&mut input)
+        .await
+        .context("failed to read phase data from stdin")?;
+    let phases =
+        qtop_core::parse_phases(&input).context("failed to parse phase data from stdin")?;
+    let circuit = Circuit::new(cli::STDIN_CIRCUIT_ID, phases);
+    circuit.validate().context("invalid phase data on stdin")?;
+    Ok(circuit)
+}
+
+fn print_history(
+    entries: &[history::HistoryEntry],
+    circuit_id: u64,
+    format: OutputFormat,
+    out: &mut output::Output,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            writeln!(out, "{}", serde_json::to_string(entries)?)?;
+            out.flush()?;
+            return Ok(());
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            if entries.is_empty() {
+                // Headers are otherwise taken from the first entry.
+                writer.write_record(["circuit_id", "winding_number", "valid", "checked_at"])?;
+            }
+            for entry in entries {
+                writer.serialize(entry)?;
+            }
+            writer.flush()?;
+            return Ok(());
+        }
+        OutputFormat::Text => {}
+    }
+
+    writeln!(
+        out,
+        "{} {} recorded check(s) for circuit {}",
+        Glyph::History,
+        entries.len(),
+        circuit_id
+    )?;
+    for entry in entries {
+        let marker = if entry.valid { Glyph::Ok } else { Glyph::Fail };
+        writeln!(
+            out,
+            "{} {} winding number {}",
+            marker,
+            entry.checked_at.to_rfc3339(),
+            entry.winding_number
+        )?;
+    }
+    out.flush()?;
     Ok(())
 }
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, Registry, TextEncoder};
+
+/// Prometheus metrics exported by monitoring mode.
+pub struct Metrics {
+    registry: Registry,
+    pub circuits_checked: IntCounter,
+    pub winding_violations: IntCounter,
+    pub winding_drift: IntCounter,
+    pub verification_duration: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let circuits_checked = IntCounter::new(
+            "qtop_circuits_checked_total",
+            "Number of circuit winding number verifications performed",
+        )?;
+        let winding_violations = IntCounter::new(
+            "qtop_winding_violations_total",
+            "Number of verifications that found an invalid winding number",
+        )?;
+        let winding_drift = IntCounter::new(
+            "qtop_winding_drift_total",
+            "Number of times a circuit's winding number changed from one check to the next",
+        )?;
+        let verification_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "qtop_verification_duration_seconds",
+                "Time spent computing and checking a circuit's winding number",
+            )
+            .buckets(prometheus::exponential_buckets(1e-6, 4.0, 12)?),
+        )?;
+
+        registry.register(Box::new(circuits_checked.clone()))?;
+        registry.register(Box::new(winding_violations.clone()))?;
+        registry.register(Box::new(winding_drift.clone()))?;
+        registry.register(Box::new(verification_duration.clone()))?;
+
+        Ok(Self {
+            registry,
+            circuits_checked,
+            winding_violations,
+            winding_drift,
+            verification_duration,
+        })
+    }
+
+    /// Render all registered metrics in the Prometheus text format.
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
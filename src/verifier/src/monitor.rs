@@ -0,0 +1,317 @@
+use crate::alert::Alerter;
+use crate::check::{self, CheckOutcome};
+use crate::cli::MonitorArgs;
+use crate::config::MonitorSettings;
+use crate::grpc::{self, VerifierService};
+use crate::metrics::Metrics;
+use crate::output;
+use crate::schedule::{self, Schedule, Scheduler};
+use crate::server::{self, MonitorState};
+use crate::ui::Glyph;
+use anyhow::{Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use qtop_core::{Circuit, Invariant};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use tracing::{info, instrument, warn};
+
+pub async fn start_monitoring(
+    mut args: MonitorArgs,
+    mut circuits: Vec<Circuit>,
+    settings: &MonitorSettings,
+) -> Result<()> {
+    let addr = SocketAddr::new(args.bind(), args.port());
+    let mut state =
+        MonitorState::new(&circuits, Metrics::new()?).with_drain_timeout(args.drain_timeout());
+    if !args.no_cache {
+        state = state.with_cache(Arc::default());
+    }
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(
+            RustlsConfig::from_pem_file(cert, key)
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to load TLS certificate {} and key {}",
+                        cert.display(),
+                        key.display()
+                    )
+                })?,
+        ),
+        _ => None,
+    };
+    let https = tls.is_some();
+    let listener = bind(addr, "monitoring server").await?;
+    let (stop_server, server_stopped) = oneshot::channel::<()>();
+    let limits = server::Limits {
+        request_timeout: args.request_timeout,
+        max_connections: args.max_connections.get(),
+    };
+    let server = tokio::spawn(server::serve(
+        listener,
+        state.clone(),
+        limits,
+        tls.clone(),
+        async {
+            let _ = server_stopped.await;
+        },
+    ));
+    let grpc = match args.grpc_port {
+        Some(grpc_port) => {
+            let listener = bind(SocketAddr::new(addr.ip(), grpc_port), "gRPC server").await?;
+            let (stop, stopped) = oneshot::channel::<()>();
+            let service = VerifierService::new(state.clone(), args.timeout);
+            let server = tokio::spawn(grpc::serve(listener, service, limits, tls, async {
+                let _ = stopped.await;
+            }));
+            Some((stop, server))
+        }
+        None => None,
+    };
+    info!(
+        %addr,
+        grpc_port = args.grpc_port,
+        https,
+        interval = ?args.interval(),
+        circuits = circuits.len(),
+        "monitoring started"
+    );
+
+    let schedule = Arc::new(Schedule::new(schedule_settings(&args), alerter(&args)));
+    let mut scheduler = Scheduler::new(state.clone(), schedule.clone());
+    let mut added = state.registry.subscribe();
+
+    let shutdown = shutdown_signal()?;
+    tokio::pin!(shutdown);
+    let mut reload = ReloadSignal::new()?;
+
+    loop {
+        scheduler.sync().await;
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = added.changed() => {}
+            _ = reload.recv() => {
+                match reload_settings(settings, &args, &circuits, &state).await {
+                    Ok((reloaded, reloaded_circuits)) => {
+                        if reloaded.alert_webhook != args.alert_webhook
+                            || reloaded.alert_rate != args.alert_rate
+                        {
+                            schedule.set_alerter(alerter(&reloaded));
+                        }
+                        schedule.set_settings(schedule_settings(&reloaded));
+                        args = reloaded;
+                        circuits = reloaded_circuits;
+                    }
+                    Err(e) => warn!(
+                        error = format!("{:#}", e),
+                        "reload failed, keeping the previous configuration"
+                    ),
+                }
+            }
+        }
+    }
+
+    scheduler.stop().await;
+    let checked = schedule.checked();
+    let _ = stop_server.send(());
+    let grpc = grpc.map(|(stop, server)| {
+        let _ = stop.send(());
+        server
+    });
+    server.await?.context("monitoring server failed")?;
+    if let Some(server) = grpc {
+        server.await?.context("gRPC server failed")?;
+    }
+
+    info!(checked, "monitoring stopped");
+    output::progress(
+        args.stream,
+        format_args!(
+            "{} Monitor stopped, {} circuits checked",
+            Glyph::Stop,
+            checked
+        ),
+    );
+    Ok(())
+}
+
+/// Listen on `addr`, naming it and the `server` that wanted it on failure,
+/// e.g. when the port is already in use.
+async fn bind(addr: SocketAddr, server: &str) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind {} to {}", server, addr))
+}
+
+fn schedule_settings(args: &MonitorArgs) -> schedule::Settings {
+    schedule::Settings {
+        interval: args.interval(),
+        timeout: args.timeout,
+        stream: args.stream,
+    }
+}
+
+fn alerter(args: &MonitorArgs) -> Option<Alerter> {
+    args.alert_webhook
+        .clone()
+        .map(|url| Alerter::new(url, args.retry.policy(), args.alert_rate))
+}
+
+/// Re-read the config and circuits files and bring the registry in line
+/// with them. Nothing is changed unless both files load.
+async fn reload_settings(
+    settings: &MonitorSettings,
+    current: &MonitorArgs,
+    circuits: &[Circuit],
+    state: &MonitorState,
+) -> Result<(MonitorArgs, Vec<Circuit>)> {
+    let args = settings.load()?;
+    let reloaded = crate::load_circuits(args.circuits.as_deref())?;
+    if args.bind() != current.bind()
+        || args.port() != current.port()
+        || args.grpc_port != current.grpc_port
+        || args.tls_cert != current.tls_cert
+        || args.tls_key != current.tls_key
+    {
+        warn!("changes to the address, port or TLS settings take effect after a restart");
+    }
+    let reconciled = state.registry.reconcile(circuits, &reloaded).await;
+    info!(
+        interval = ?args.interval(),
+        added = reconciled.added,
+        removed = reconciled.removed,
+        updated = reconciled.updated,
+        "configuration reloaded"
+    );
+    Ok((args, reloaded))
+}
+
+/// Verify one circuit, updating its status and the exported metrics. A
+/// circuit that could not be verified, e.g. because it timed out, keeps
+/// its previous status. Returns `None` without verifying if the circuit is
+/// no longer monitored or is draining.
+#[instrument(skip_all, fields(circuit_id = circuit.id))]
+pub async fn verify_circuit(
+    state: &MonitorState,
+    circuit: &Circuit,
+    timeout: Option<Duration>,
+) -> Option<CheckOutcome> {
+    // Held until the result is recorded, so a drain never sees a
+    // half-finished verification.
+    let _verifying = state.registry.begin(circuit.id).await?;
+    let started = Instant::now();
+    let outcome = check::verify_isolated(
+        circuit.clone(),
+        Invariant::Winding,
+        0,
+        None,
+        timeout,
+        state.cache.clone(),
+    )
+    .await;
+    let elapsed = started.elapsed();
+    state
+        .metrics
+        .verification_duration
+        .observe(elapsed.as_secs_f64());
+    state.metrics.circuits_checked.inc();
+
+    let CheckOutcome::Verified(result) = &outcome else {
+        return Some(outcome);
+    };
+
+    if !result.valid {
+        state.metrics.winding_violations.inc();
+        warn!(
+            winding_number = result.winding_number,
+            "winding number violation detected"
+        );
+    }
+    if let Some(drift) = state.registry.record(result.clone(), elapsed).await {
+        state.metrics.winding_drift.inc();
+        warn!(
+            previous_winding = drift.previous,
+            winding_number = drift.current,
+            "DRIFT: winding number changed since the last check"
+        );
+    }
+    Some(outcome)
+}
+
+/// Resolves on the first SIGINT, or SIGTERM on Unix. The handlers are
+/// registered before this returns so a signal arriving mid-check is not
+/// lost.
+#[cfg(unix)]
+pub fn shutdown_signal() -> Result<impl std::future::Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    Ok(async move {
+        tokio::select! {
+            _ = interrupt.recv() => {}
+            _ = terminate.recv() => {}
+        }
+    })
+}
+
+#[cfg(not(unix))]
+pub fn shutdown_signal() -> Result<impl std::future::Future<Output = ()>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+/// Delivers SIGHUP, the request to reload configuration. Never fires where
+/// there is no SIGHUP.
+struct ReloadSignal {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    #[cfg(unix)]
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Result<Self> {
+        Ok(Self {})
+    }
+
+    #[cfg(unix)]
+    async fn recv(&mut self) {
+        self.hangup.recv().await;
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) {
+        std::future::pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_bind_names_the_address() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = taken.local_addr().unwrap();
+        let err = bind(addr, "monitoring server").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("failed to bind monitoring server to {}", addr)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Where a command writes its results: stdout, or the file given with
+/// `--output`.
+pub enum Output {
+    Stdout(io::Stdout),
+    File(BufWriter<File>),
+}
+
+impl Output {
+    /// Create (or truncate) the file at `path`, or write to stdout when no
+    /// path is given.
+    pub fn open(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("failed to create output file {}", path.display()))?;
+                Ok(Output::File(BufWriter::new(file)))
+            }
+            None => Ok(Output::Stdout(io::stdout())),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::File(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::File(out) => out.flush(),
+        }
+    }
+}
+
+/// Print a human-readable progress line. It shares stdout with the results
+/// by default, and moves to stderr once the results are `redirected` to a
+/// file so that stdout and the file stay free of each other's output.
+pub fn progress(redirected: bool, line: fmt::Arguments) {
+    if redirected {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Minimum time between two fallback progress log lines.
+const PROGRESS_LOG_EVERY: Duration = Duration::from_secs(5);
+
+/// How far a batch has got: a progress bar on an interactive terminal, and
+/// otherwise an occasional "N/M done" log line, so that CI logs still show
+/// the run moving. Batches done within a few seconds log nothing.
+pub enum BatchProgress {
+    Bar(ProgressBar),
+    Log {
+        total: usize,
+        done: usize,
+        last: Instant,
+    },
+}
+
+impl BatchProgress {
+    /// Draw a bar on stderr if `interactive`, or log lines otherwise.
+    pub fn new(total: usize, interactive: bool) -> Self {
+        if !interactive {
+            return BatchProgress::Log {
+                total,
+                done: 0,
+                last: Instant::now(),
+            };
+        }
+        let bar = ProgressBar::new(total as u64);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} circuits, ETA {eta}")
+                .expect("progress template is valid"),
+        );
+        BatchProgress::Bar(bar)
+    }
+
+    pub fn inc(&mut self) {
+        match self {
+            BatchProgress::Bar(bar) => bar.inc(1),
+            BatchProgress::Log { total, done, last } => {
+                *done += 1;
+                if last.elapsed() >= PROGRESS_LOG_EVERY && *done < *total {
+                    info!("{}/{} done", done, total);
+                    *last = Instant::now();
+                }
+            }
+        }
+    }
+
+    /// Run `f`, which prints to the terminal, with the bar hidden so the
+    /// two don't garble each other.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match self {
+            BatchProgress::Bar(bar) => bar.suspend(f),
+            BatchProgress::Log { .. } => f(),
+        }
+    }
+
+    pub fn finish(self) {
+        if let BatchProgress::Bar(bar) = self {
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::ui::Glyph;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use qtop_core::{Circuit, Invariant, VerificationResult, ALGORITHM_VERSION};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// A reproducible record of a `check` run: the inputs of every verified
+/// circuit together with what was computed from them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofFile {
+    pub created_at: DateTime<Utc>,
+    /// [`ALGORITHM_VERSION`] of the core library that produced the results.
+    pub algorithm_version: u32,
+    pub verifier_version: String,
+    pub proofs: Vec<Proof>,
+}
+
+/// The verification of one circuit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proof {
+    pub circuit_id: u64,
+    pub invariant: Invariant,
+    pub winding_number: i64,
+    pub expected: Option<i64>,
+    pub tolerance: u64,
+    pub valid: bool,
+    /// SHA-256 of the circuit definition below, as JSON.
+    pub circuit_sha256: String,
+    /// SHA-256 of the samples the invariant was computed from: the phases
+    /// for a winding number, the Berry flux for a Chern number.
+    pub phase_data_sha256: String,
+    pub circuit: Circuit,
+}
+
+impl Proof {
+    pub fn new(circuit: &Circuit, result: &VerificationResult, tolerance: u64) -> Result<Self> {
+        Ok(Self {
+            circuit_id: result.circuit_id,
+            invariant: result.invariant,
+            winding_number: result.winding_number,
+            expected: result.expected_winding,
+            tolerance,
+            valid: result.valid,
+            circuit_sha256: circuit_digest(circuit)?,
+            phase_data_sha256: phase_data_digest(circuit, result.invariant),
+            circuit: circuit.clone(),
+        })
+    }
+}
+
+impl ProofFile {
+    pub fn new(proofs: Vec<Proof>) -> Self {
+        Self {
+            created_at: Utc::now(),
+            algorithm_version: ALGORITHM_VERSION,
+            verifier_version: env!("CARGO_PKG_VERSION").to_string(),
+            proofs,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create proof file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer
+            .flush()
+            .with_context(|| format!("failed to write proof file {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read proof file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse proof file {}", path.display()))
+    }
+}
+
+fn circuit_digest(circuit: &Circuit) -> Result<String> {
+    Ok(hex_digest(&serde_json::to_vec(circuit)?))
+}
+
+fn phase_data_digest(circuit: &Circuit, invariant: Invariant) -> String {
+    let samples = match invariant {
+        Invariant::Winding => &circuit.phases,
+        Invariant::Chern => &circuit.berry_flux,
+    };
+    let bytes: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+    hex_digest(&bytes)
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Why a recorded proof no longer holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The recorded circuit no longer matches its recorded digest.
+    CircuitModified,
+    /// The recorded samples no longer match their recorded digest.
+    PhaseDataModified,
+    /// Re-running the computation gave a different answer.
+    Result { winding_number: i64, valid: bool },
+}
+
+/// Re-run the computation recorded in `proof` and compare the outcome.
+pub fn verify(proof: &Proof) -> Result<Option<Divergence>> {
+    if circuit_digest(&proof.circuit)? != proof.circuit_sha256 {
+        return Ok(Some(Divergence::CircuitModified));
+    }
+    if phase_data_digest(&proof.circuit, proof.invariant) != proof.phase_data_sha256 {
+        return Ok(Some(Divergence::PhaseDataModified));
+    }
+
+    let mut circuit = proof.circuit.clone();
+    match proof.invariant {
+        Invariant::Winding => circuit.expected_winding = proof.expected,
+        Invariant::Chern => circuit.expected_chern = proof.expected,
+    }
+    let result = circuit.verify_invariant(proof.invariant, proof.tolerance);
+    if result.winding_number != proof.winding_number || result.valid != proof.valid {
+        return Ok(Some(Divergence::Result {
+            winding_number: result.winding_number,
+            valid: result.valid,
+        }));
+    }
+    Ok(None)
+}
+
+/// Check every proof in `file`, printing one line per circuit. Returns
+/// whether all of them still hold.
+pub fn report(file: &ProofFile) -> Result<bool> {
+    if file.algorithm_version != ALGORITHM_VERSION {
+        println!(
+            "{} Proofs were produced by algorithm version {}; re-running with version {}",
+            Glyph::Info,
+            file.algorithm_version,
+            ALGORITHM_VERSION
+        );
+    }
+
+    let mut holds = true;
+    for proof in &file.proofs {
+        match verify(proof)? {
+            None => println!(
+                "{} Circuit {} proof holds: {} {} ({})",
+                Glyph::Ok,
+                proof.circuit_id,
+                proof.invariant,
+                proof.winding_number,
+                if proof.valid { "valid" } else { "invalid" }
+            ),
+            Some(divergence) => {
+                holds = false;
+                let reason = match divergence {
+                    Divergence::CircuitModified => {
+                        "circuit definition does not match its digest".to_string()
+                    }
+                    Divergence::PhaseDataModified => {
+                        "phase data does not match its digest".to_string()
+                    }
+                    Divergence::Result {
+                        winding_number,
+                        valid,
+                    } => format!(
+                        "recorded {} {} ({}), recomputed {} ({})",
+                        proof.invariant,
+                        proof.winding_number,
+                        if proof.valid { "valid" } else { "invalid" },
+                        winding_number,
+                        if valid { "valid" } else { "invalid" }
+                    ),
+                };
+                println!(
+                    "{} Circuit {} proof diverges: {}",
+                    Glyph::Fail,
+                    proof.circuit_id,
+                    reason
+                );
+            }
+        }
+    }
+    Ok(holds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    fn recorded() -> Proof {
+        // Phases with no short decimal form, to exercise the round trip.
+        let phases = (0..7).map(|i| TAU * i as f64 / 7.0).collect();
+        let circuit = Circuit::new(1, phases).with_expected_winding(1);
+        let result = circuit.verify();
+        Proof::new(&circuit, &result, 0).unwrap()
+    }
+
+    #[test]
+    fn proof_survives_a_round_trip_through_json() {
+        let file = ProofFile::new(vec![recorded()]);
+        let json = serde_json::to_string(&file).unwrap();
+        let read: ProofFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(verify(&read.proofs[0]).unwrap(), None);
+    }
+
+    #[test]
+    fn edited_inputs_or_results_diverge() {
+        let mut proof = recorded();
+        proof.circuit.phases[3] += 0.5;
+        assert_eq!(verify(&proof).unwrap(), Some(Divergence::CircuitModified));
+
+        let mut proof = recorded();
+        proof.winding_number = 2;
+        assert_eq!(
+            verify(&proof).unwrap(),
+            Some(Divergence::Result {
+                winding_number: 1,
+                valid: true
+            })
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::registry::CircuitStatus;
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use qtop_core::VerificationResult;
+use reqwest::{StatusCode, Url};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::warn;
+
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many peers must agree, written `2of3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quorum {
+    pub required: usize,
+    pub total: usize,
+}
+
+impl Quorum {
+    /// A simple majority of `total` peers.
+    pub fn majority(total: usize) -> Self {
+        Self {
+            required: total / 2 + 1,
+            total,
+        }
+    }
+}
+
+impl FromStr for Quorum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid quorum `{}`: expected e.g. `2of3`", s);
+        let (required, total) = s.split_once("of").ok_or_else(invalid)?;
+        let required: usize = required.trim().parse().map_err(|_| invalid())?;
+        let total: usize = total.trim().parse().map_err(|_| invalid())?;
+        if required == 0 || required > total {
+            return Err(format!(
+                "invalid quorum `{}`: need between 1 and {} agreeing peers",
+                s, total
+            ));
+        }
+        Ok(Self { required, total })
+    }
+}
+
+impl fmt::Display for Quorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}of{}", self.required, self.total)
+    }
+}
+
+/// What one peer reported for a circuit.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerAnswer {
+    pub peer: String,
+    pub winding_number: Option<i64>,
+    /// Why the peer gave no usable answer, if it did not.
+    pub error: Option<String>,
+}
+
+/// How the peers voted on one circuit's locally computed result.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuorumVote {
+    pub required: usize,
+    pub agreeing: usize,
+    pub reached: bool,
+    pub peers: Vec<PeerAnswer>,
+}
+
+impl QuorumVote {
+    /// Whether any peer failed to agree, which is worth reporting even
+    /// when the quorum was reached.
+    pub fn is_split(&self) -> bool {
+        self.agreeing < self.peers.len()
+    }
+}
+
+/// Cross-checks local results against the `/circuit/{id}` endpoint of
+/// independent verifier instances.
+pub struct PeerCheck {
+    client: reqwest::Client,
+    peers: Vec<Url>,
+    quorum: Quorum,
+}
+
+impl PeerCheck {
+    pub fn new(peers: Vec<Url>, quorum: Option<Quorum>) -> Result<Self> {
+        let quorum = quorum.unwrap_or_else(|| Quorum::majority(peers.len()));
+        if quorum.total != peers.len() {
+            return Err(anyhow!(
+                "quorum {} does not match the {} peer(s) given",
+                quorum,
+                peers.len()
+            ));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(PEER_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Ok(Self {
+            client,
+            peers,
+            quorum,
+        })
+    }
+
+    /// Ask every peer, in parallel, for its answer on `local`'s circuit
+    /// and count how many agree with it.
+    pub async fn vote(&self, local: &VerificationResult) -> QuorumVote {
+        let answers = join_all(self.peers.iter().map(|peer| self.ask(peer, local))).await;
+        tally(local, answers, self.quorum)
+    }
+
+    async fn ask(&self, peer: &Url, local: &VerificationResult) -> PeerAnswer {
+        let answer = match self.query(peer, local).await {
+            Ok(winding_number) => (Some(winding_number), None),
+            Err(e) => {
+                warn!(circuit_id = local.circuit_id, %peer, error = %e, "peer gave no answer");
+                (None, Some(e))
+            }
+        };
+        PeerAnswer {
+            peer: peer.to_string(),
+            winding_number: answer.0,
+            error: answer.1,
+        }
+    }
+
+    async fn query(&self, peer: &Url, local: &VerificationResult) -> Result<i64, String> {
+        let url = peer
+            .join(&format!("circuit/{}", local.circuit_id))
+            .map_err(|e| e.to_string())?;
+        let response = self.client.get(url).send().await.map_err(describe)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err("circuit not monitored".to_string());
+        }
+        let status: CircuitStatus = response
+            .error_for_status()
+            .map_err(describe)?
+            .json()
+            .await
+            .map_err(describe)?;
+        match status.last_result {
+            None => Err("not verified yet".to_string()),
+            Some(result) if result.invariant != local.invariant => {
+                Err(format!("measured the {} invariant", result.invariant))
+            }
+            Some(result) => Ok(result.winding_number),
+        }
+    }
+}
+
+/// The error with its causes, which for connection failures hold the
+/// useful part.
+fn describe(e: reqwest::Error) -> String {
+    format!("{:#}", anyhow::Error::from(e))
+}
+
+fn tally(local: &VerificationResult, peers: Vec<PeerAnswer>, quorum: Quorum) -> QuorumVote {
+    let agreeing = peers
+        .iter()
+        .filter(|answer| answer.winding_number == Some(local.winding_number))
+        .count();
+    QuorumVote {
+        required: quorum.required,
+        agreeing,
+        reached: agreeing >= quorum.required,
+        peers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qtop_core::Invariant;
+
+    fn answer(peer: &str, winding_number: Option<i64>) -> PeerAnswer {
+        PeerAnswer {
+            peer: peer.to_string(),
+            winding_number,
+            error: winding_number.is_none().then(|| "unreachable".to_string()),
+        }
+    }
+
+    #[test]
+    fn quorum_parses_m_of_n() {
+        assert_eq!(
+            "2of3".parse(),
+            Ok(Quorum {
+                required: 2,
+                total: 3
+            })
+        );
+        assert!("0of3".parse::<Quorum>().is_err());
+        assert!("4of3".parse::<Quorum>().is_err());
+        assert!("two".parse::<Quorum>().is_err());
+        assert_eq!(Quorum::majority(4).required, 3);
+    }
+
+    #[test]
+    fn split_vote_is_counted_per_peer() {
+        let local = VerificationResult {
+            circuit_id: 1,
+            invariant: Invariant::Winding,
+            winding_number: 1,
+            expected_winding: Some(1),
+            confidence: None,
+            valid: true,
+        };
+        let peers = vec![
+            answer("http://a:9090", Some(1)),
+            answer("http://b:9090", Some(2)),
+            answer("http://c:9090", None),
+        ];
+
+        let vote = tally(&local, peers.clone(), "2of3".parse().unwrap());
+        assert_eq!(vote.agreeing, 1);
+        assert!(!vote.reached);
+
+        let vote = tally(&local, peers, "1of3".parse().unwrap());
+        assert!(vote.reached);
+        assert!(vote.is_split());
+    }
+}
@@ -0,0 +1,382 @@
+use crate::latency::{LatencySummary, LatencyWindow};
+use chrono::{DateTime, Utc};
+use qtop_core::{Circuit, VerificationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, OwnedRwLockReadGuard, RwLock};
+
+/// Last known verification state of a monitored circuit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitStatus {
+    pub circuit_id: u64,
+    pub last_result: Option<VerificationResult>,
+    pub last_checked: Option<DateTime<Utc>>,
+    /// How long this circuit's recent verifications took.
+    pub latency: Option<LatencySummary>,
+    /// Being shut down: no new verifications start, and the circuit is
+    /// removed once the running ones finish.
+    #[serde(default)]
+    pub draining: bool,
+}
+
+struct Entry {
+    circuit: Circuit,
+    status: CircuitStatus,
+    latencies: LatencyWindow,
+    /// Winding number the next result is compared against for drift;
+    /// unset until the first result, and again after the circuit's
+    /// definition changes.
+    baseline: Option<i64>,
+    /// Read-held by every verification of the circuit in progress, so a
+    /// drain can wait for them by taking the write lock.
+    in_flight: Arc<RwLock<()>>,
+}
+
+impl Entry {
+    fn new(circuit: Circuit) -> Self {
+        let status = CircuitStatus {
+            circuit_id: circuit.id,
+            last_result: None,
+            last_checked: None,
+            latency: None,
+            draining: false,
+        };
+        Self {
+            circuit,
+            status,
+            latencies: LatencyWindow::default(),
+            baseline: None,
+            in_flight: Arc::default(),
+        }
+    }
+}
+
+/// How [`CircuitRegistry::reconcile`] changed the registry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reconciled {
+    pub added: usize,
+    pub removed: usize,
+    pub updated: usize,
+}
+
+/// A circuit's winding number changing from one verification to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Drift {
+    pub previous: i64,
+    pub current: i64,
+}
+
+/// How [`CircuitRegistry::drain`] removed a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drained {
+    /// Every verification in progress finished first.
+    Cleanly,
+    /// Verifications were still running at the drain timeout; their
+    /// results are discarded.
+    Forced,
+}
+
+/// Held for the duration of one verification; see
+/// [`CircuitRegistry::begin`].
+pub type Verifying = OwnedRwLockReadGuard<()>;
+
+/// The set of circuits under active monitoring and their latest results.
+/// Cloning is cheap and every clone shares the same set, so circuits added
+/// or removed through the HTTP server are seen by the monitor's scheduler
+/// straight away.
+#[derive(Clone)]
+pub struct CircuitRegistry {
+    entries: Arc<RwLock<BTreeMap<u64, Entry>>>,
+    /// Signalled whenever circuits are added, so the monitor can schedule
+    /// them.
+    added: Arc<watch::Sender<()>>,
+}
+
+impl CircuitRegistry {
+    pub fn new(circuits: &[Circuit]) -> Self {
+        let entries = circuits
+            .iter()
+            .map(|circuit| (circuit.id, Entry::new(circuit.clone())))
+            .collect();
+        Self {
+            entries: Arc::new(RwLock::new(entries)),
+            added: Arc::new(watch::Sender::new(())),
+        }
+    }
+
+    /// A receiver that sees a change each time circuits are added.
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.added.subscribe()
+    }
+
+    /// Start monitoring `circuit`. Returns `false`, leaving the registry
+    /// unchanged, if a circuit with the same id is already registered.
+    pub async fn add(&self, circuit: Circuit) -> bool {
+        let mut entries = self.entries.write().await;
+        if entries.contains_key(&circuit.id) {
+            return false;
+        }
+        entries.insert(circuit.id, Entry::new(circuit));
+        self.added.send_replace(());
+        true
+    }
+
+    /// Stop monitoring a circuit. Returns whether it was being monitored.
+    pub async fn remove(&self, id: u64) -> bool {
+        self.entries.write().await.remove(&id).is_some()
+    }
+
+    /// Stop monitoring a circuit once the verifications of it in progress
+    /// have finished, or after `timeout` regardless. No new verification
+    /// of the circuit starts meanwhile. Returns `None` if it was not being
+    /// monitored.
+    ///
+    /// Once started, the drain runs to completion in a task of its own, so
+    /// a caller that gives up waiting, such as a timed-out request, cannot
+    /// leave the circuit draining forever.
+    pub async fn drain(&self, id: u64, timeout: Duration) -> Option<Drained> {
+        let in_flight = {
+            let mut entries = self.entries.write().await;
+            let entry = entries.get_mut(&id)?;
+            entry.status.draining = true;
+            entry.in_flight.clone()
+        };
+        let registry = self.clone();
+        let removal = tokio::spawn(async move {
+            let drained = match tokio::time::timeout(timeout, in_flight.write_owned()).await {
+                Ok(_) => Drained::Cleanly,
+                Err(_) => Drained::Forced,
+            };
+            registry.entries.write().await.remove(&id);
+            drained
+        });
+        // Only fails if the runtime is shutting down, taking the registry
+        // with it.
+        Some(removal.await.unwrap_or(Drained::Forced))
+    }
+
+    /// Mark a verification of circuit `id` as started. Returns `None`,
+    /// meaning it should not run, if the circuit is no longer monitored or
+    /// is draining; otherwise a drain waits until the guard is dropped.
+    pub async fn begin(&self, id: u64) -> Option<Verifying> {
+        let entries = self.entries.read().await;
+        let entry = entries.get(&id).filter(|entry| !entry.status.draining)?;
+        entry.in_flight.clone().try_read_owned().ok()
+    }
+
+    /// Bring the registry in line with a circuits file that changed from
+    /// `previous` to `current`. Circuits that left the file are removed,
+    /// new ones added, and changed definitions replaced while keeping their
+    /// status; circuits registered by other means are left alone, and
+    /// circuits shut down or removed since the file was loaded stay gone.
+    pub async fn reconcile(&self, previous: &[Circuit], current: &[Circuit]) -> Reconciled {
+        let mut entries = self.entries.write().await;
+        let mut reconciled = Reconciled::default();
+        for old in previous {
+            if !current.iter().any(|circuit| circuit.id == old.id)
+                && entries.remove(&old.id).is_some()
+            {
+                reconciled.removed += 1;
+            }
+        }
+        for circuit in current {
+            match entries.get_mut(&circuit.id) {
+                Some(entry) if entry.circuit == *circuit => {}
+                Some(entry) => {
+                    // A redefined circuit may legitimately wind differently.
+                    entry.circuit = circuit.clone();
+                    entry.baseline = None;
+                    reconciled.updated += 1;
+                }
+                None if !previous.iter().any(|old| old.id == circuit.id) => {
+                    entries.insert(circuit.id, Entry::new(circuit.clone()));
+                    reconciled.added += 1;
+                }
+                None => {}
+            }
+        }
+        if reconciled.added > 0 {
+            self.added.send_replace(());
+        }
+        reconciled
+    }
+
+    /// Snapshot of the circuits currently under monitoring.
+    pub async fn circuits(&self) -> Vec<Circuit> {
+        let entries = self.entries.read().await;
+        entries
+            .values()
+            .map(|entry| entry.circuit.clone())
+            .collect()
+    }
+
+    /// Status of every registered circuit, ordered by id.
+    pub async fn statuses(&self) -> Vec<CircuitStatus> {
+        let entries = self.entries.read().await;
+        entries.values().map(|entry| entry.status.clone()).collect()
+    }
+
+    pub async fn circuit(&self, id: u64) -> Option<Circuit> {
+        let entries = self.entries.read().await;
+        entries.get(&id).map(|entry| entry.circuit.clone())
+    }
+
+    pub async fn status(&self, id: u64) -> Option<CircuitStatus> {
+        let entries = self.entries.read().await;
+        entries.get(&id).map(|entry| entry.status.clone())
+    }
+
+    /// Store a verification result and how long it took to compute,
+    /// returning the drift from the previously recorded winding number if
+    /// it changed. Results for circuits that were removed while being
+    /// verified are dropped.
+    pub async fn record(&self, result: VerificationResult, elapsed: Duration) -> Option<Drift> {
+        let mut entries = self.entries.write().await;
+        let entry = entries.get_mut(&result.circuit_id)?;
+        let current = result.winding_number;
+        let drift = entry
+            .baseline
+            .replace(current)
+            .filter(|&previous| previous != current)
+            .map(|previous| Drift { previous, current });
+        entry.latencies.push(elapsed);
+        entry.status.latency = entry.latencies.summary();
+        entry.status.last_result = Some(result);
+        entry.status.last_checked = Some(Utc::now());
+        drift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clones_share_the_registered_set() {
+        let registry = CircuitRegistry::new(&[Circuit::new(1, vec![])]);
+        let loop_view = registry.clone();
+
+        assert!(registry.add(Circuit::new(2, vec![])).await);
+        assert!(!registry.add(Circuit::new(1, vec![0.5])).await);
+        let ids: Vec<u64> = loop_view.circuits().await.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [1, 2]);
+        // The duplicate did not replace the original definition.
+        assert!(loop_view.circuits().await[0].phases.is_empty());
+
+        assert!(registry.remove(1).await);
+        assert!(!registry.remove(1).await);
+        assert!(loop_view.status(1).await.is_none());
+        assert_eq!(loop_view.statuses().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn abandoned_drain_still_removes_the_circuit() {
+        let registry = CircuitRegistry::new(&[Circuit::new(1, vec![])]);
+        let verifying = registry.begin(1).await.unwrap();
+        let drain = registry.drain(1, Duration::from_secs(60));
+        assert!(tokio::time::timeout(Duration::from_millis(20), drain)
+            .await
+            .is_err());
+
+        drop(verifying);
+        for _ in 0..100 {
+            if registry.status(1).await.is_none() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("circuit 1 was left draining");
+    }
+
+    #[tokio::test]
+    async fn reconcile_applies_file_changes_only() {
+        let previous = [Circuit::new(1, vec![]), Circuit::new(2, vec![])];
+        let registry = CircuitRegistry::new(&previous);
+        // Added at runtime, not from the file.
+        registry.add(Circuit::new(9, vec![])).await;
+
+        let current = [Circuit::new(2, vec![0.5]), Circuit::new(3, vec![])];
+        let reconciled = registry.reconcile(&previous, &current).await;
+        assert_eq!(
+            reconciled,
+            Reconciled {
+                added: 1,
+                removed: 1,
+                updated: 1
+            }
+        );
+        let circuits = registry.circuits().await;
+        let ids: Vec<u64> = circuits.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [2, 3, 9]);
+        assert_eq!(circuits[0].phases, [0.5]);
+    }
+
+    #[tokio::test]
+    async fn reconcile_keeps_shut_down_circuits_gone() {
+        let circuits = [Circuit::new(1, vec![]), Circuit::new(2, vec![])];
+        let registry = CircuitRegistry::new(&circuits);
+        registry.drain(1, Duration::ZERO).await.unwrap();
+        registry.remove(2).await;
+
+        let reconciled = registry.reconcile(&circuits, &circuits).await;
+        assert_eq!(reconciled, Reconciled::default());
+        assert!(registry.circuits().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn changed_winding_is_drift_after_the_baseline() {
+        let circuit = Circuit::new(1, vec![]);
+        let registry = CircuitRegistry::new(std::slice::from_ref(&circuit));
+        let record = |winding_number| {
+            let result = VerificationResult {
+                circuit_id: 1,
+                invariant: Default::default(),
+                winding_number,
+                expected_winding: None,
+                confidence: None,
+                valid: true,
+            };
+            registry.record(result, Duration::ZERO)
+        };
+
+        assert_eq!(record(1).await, None);
+        assert_eq!(record(1).await, None);
+        assert_eq!(
+            record(2).await,
+            Some(Drift {
+                previous: 1,
+                current: 2
+            })
+        );
+
+        // Redefining the circuit starts a new baseline.
+        let redefined = [Circuit::new(1, vec![0.5])];
+        registry.reconcile(&[circuit], &redefined).await;
+        assert_eq!(record(3).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_waits_for_verifications_in_progress() {
+        let registry = CircuitRegistry::new(&[Circuit::new(1, vec![]), Circuit::new(2, vec![])]);
+        let timeout = Duration::from_secs(5);
+
+        let verifying = registry.begin(1).await.unwrap();
+        let drain = tokio::spawn({
+            let registry = registry.clone();
+            async move { registry.drain(1, timeout).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(registry.status(1).await.unwrap().draining);
+        assert!(registry.begin(1).await.is_none());
+        drop(verifying);
+        assert_eq!(drain.await.unwrap(), Some(Drained::Cleanly));
+        assert!(registry.status(1).await.is_none());
+
+        let _stuck = registry.begin(2).await.unwrap();
+        assert_eq!(registry.drain(2, timeout).await, Some(Drained::Forced));
+        assert!(registry.status(2).await.is_none());
+        assert_eq!(registry.drain(2, timeout).await, None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Bounded retry with exponential backoff for network requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; zero disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub base_delay: Duration,
+}
+
+/// The last error of an operation that kept failing.
+#[derive(Debug)]
+pub struct Exhausted<E> {
+    pub attempts: u32,
+    pub source: E,
+}
+
+// The last error itself is exposed as the source, so it shows up in
+// `{:#}` chains without being repeated here.
+impl<E> fmt::Display for Exhausted<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gave up after {} attempt(s)", self.attempts)
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for Exhausted<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(1 << retry.min(16))
+    }
+
+    /// Run `op` until it succeeds, fails with a non-`transient` error, or
+    /// the retries run out.
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        mut op: F,
+        transient: impl Fn(&E) -> bool,
+    ) -> Result<T, Exhausted<E>>
+    where
+        E: fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < self.max_retries && transient(&e) => {
+                    let delay = self.delay(retry);
+                    warn!(error = %e, retry = retry + 1, ?delay, "request failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                Err(source) => {
+                    return Err(Exhausted {
+                        attempts: retry + 1,
+                        source,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Send the request built by `request`, retrying connection failures and
+    /// timeouts. Any response, including an error status, is returned as is.
+    pub async fn send(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Exhausted<reqwest::Error>> {
+        self.run(
+            || request().send(),
+            |e: &reqwest::Error| e.is_connect() || e.is_timeout(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tokio::time::Instant;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(100),
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_errors_are_retried_with_backoff() {
+        let calls = Cell::new(0);
+        let started = Instant::now();
+        let result = POLICY
+            .run(
+                || {
+                    calls.set(calls.get() + 1);
+                    let ok = calls.get() == 3;
+                    async move {
+                        if ok {
+                            Ok(())
+                        } else {
+                            Err("refused")
+                        }
+                    }
+                },
+                |_| true,
+            )
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(calls.get(), 3);
+        assert_eq!(started.elapsed(), Duration::from_millis(100 + 200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_retries() {
+        let err = POLICY
+            .run(|| async { Err::<(), _>("refused") }, |_| true)
+            .await
+            .unwrap_err();
+        assert_eq!(err.attempts, 4);
+        assert_eq!(err.source, "refused");
+        assert_eq!(err.to_string(), "gave up after 4 attempt(s)");
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let calls = Cell::new(0);
+        let err = POLICY
+            .run(
+                || {
+                    calls.set(calls.get() + 1);
+                    async { Err::<(), _>("not found") }
+                },
+                |_| false,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.attempts, 1);
+        assert_eq!(calls.get(), 1);
+    }
+}
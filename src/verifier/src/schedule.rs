@@ -0,0 +1,289 @@
+use crate::alert::Alerter;
+use crate::check::CheckOutcome;
+use crate::monitor::verify_circuit;
+use crate::server::MonitorState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, warn};
+
+/// What every circuit's task reads before each check, so a reload applies
+/// from each circuit's next check without restarting the tasks.
+pub struct Schedule {
+    settings: RwLock<Settings>,
+    alerter: Mutex<Option<Alerter>>,
+    checked: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    /// Interval for circuits that don't set their own.
+    pub interval: Duration,
+    pub timeout: Option<Duration>,
+    /// Write every check's outcome to stdout as a JSON line.
+    pub stream: bool,
+}
+
+impl Schedule {
+    pub fn new(settings: Settings, alerter: Option<Alerter>) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+            alerter: Mutex::new(alerter),
+            checked: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn settings(&self) -> Settings {
+        *self.settings.read().unwrap()
+    }
+
+    pub fn set_settings(&self, settings: Settings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    /// Replace the alerter; the old one summarizes what it suppressed as it
+    /// is dropped.
+    pub fn set_alerter(&self, alerter: Option<Alerter>) {
+        *self.alerter.lock().unwrap() = alerter;
+    }
+
+    fn summarize_alerts(&self) {
+        if let Some(alerter) = self.alerter.lock().unwrap().as_mut() {
+            alerter.summarize();
+        }
+    }
+
+    /// Circuit checks completed so far, across all circuits.
+    pub fn checked(&self) -> usize {
+        self.checked.load(Ordering::Relaxed)
+    }
+}
+
+/// How often the alerter is asked whether a summary of suppressed alerts
+/// is due.
+const SUMMARY_CHECK: Duration = Duration::from_secs(1);
+
+/// Runs each monitored circuit as its own task on its own interval, so a
+/// slow or rarely checked circuit never holds up the others.
+pub struct Scheduler {
+    state: MonitorState,
+    schedule: Arc<Schedule>,
+    tasks: HashMap<u64, JoinHandle<()>>,
+    /// Sends alert summaries on time even when no results are coming in.
+    summaries: JoinHandle<()>,
+    /// Handed to the tasks started first; see [`MarkReady`].
+    initial: Option<Arc<MarkReady>>,
+}
+
+/// Marks the monitor ready when dropped. Every initially monitored
+/// circuit's task holds a clone until its first check, so the last of those
+/// checks to finish marks the monitor ready.
+struct MarkReady(MonitorState);
+
+impl Drop for MarkReady {
+    fn drop(&mut self) {
+        self.0.mark_ready();
+    }
+}
+
+impl Scheduler {
+    pub fn new(state: MonitorState, schedule: Arc<Schedule>) -> Self {
+        Self {
+            initial: Some(Arc::new(MarkReady(state.clone()))),
+            summaries: tokio::spawn(summarize(schedule.clone())),
+            state,
+            schedule,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Start a task for every registered circuit that lacks one. Tasks end
+    /// by themselves once their circuit is no longer registered.
+    pub async fn sync(&mut self) {
+        self.tasks.retain(|_, task| !task.is_finished());
+        let initial = self.initial.take();
+        for circuit in self.state.registry.circuits().await {
+            if self.tasks.contains_key(&circuit.id) {
+                continue;
+            }
+            let task = run(
+                self.state.clone(),
+                self.schedule.clone(),
+                circuit.id,
+                initial.clone(),
+            );
+            self.tasks.insert(circuit.id, tokio::spawn(task));
+        }
+    }
+
+    /// Cancel every circuit's task. A check cut short records nothing, so
+    /// each circuit keeps its last complete status. Then deliver the
+    /// summary of any alerts still suppressed.
+    pub async fn stop(self) {
+        self.summaries.abort();
+        for task in self.tasks.values() {
+            task.abort();
+        }
+        for (_, task) in self.tasks {
+            let _ = task.await;
+        }
+        let alerter = self.schedule.alerter.lock().unwrap().take();
+        if let Some(alerter) = alerter {
+            alerter.close().await;
+        }
+    }
+}
+
+async fn summarize(schedule: Arc<Schedule>) {
+    let mut ticks = tokio::time::interval(SUMMARY_CHECK);
+    loop {
+        ticks.tick().await;
+        schedule.summarize_alerts();
+    }
+}
+
+async fn run(
+    state: MonitorState,
+    schedule: Arc<Schedule>,
+    circuit_id: u64,
+    mut first_check: Option<Arc<MarkReady>>,
+) {
+    loop {
+        // Fetched afresh each time to pick up a redefinition on reload.
+        let Some(circuit) = state.registry.circuit(circuit_id).await else {
+            debug!(
+                circuit_id,
+                "circuit no longer monitored, stopping its checks"
+            );
+            return;
+        };
+        let settings = schedule.settings();
+        let started = Instant::now();
+        if let Some(outcome) = verify_circuit(&state, &circuit, settings.timeout).await {
+            schedule.checked.fetch_add(1, Ordering::Relaxed);
+            if settings.stream {
+                stream(&outcome);
+            }
+            if let CheckOutcome::Verified(result) = &outcome {
+                if let Some(alerter) = schedule.alerter.lock().unwrap().as_mut() {
+                    alerter.observe(result);
+                }
+            }
+        }
+        first_check.take();
+        sleep_until(started + circuit.interval().unwrap_or(settings.interval)).await;
+    }
+}
+
+/// One line of `--stream` output.
+#[derive(Serialize)]
+struct StreamEvent<'a> {
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    outcome: &'a CheckOutcome,
+}
+
+fn event_line(outcome: &CheckOutcome, timestamp: DateTime<Utc>) -> serde_json::Result<String> {
+    serde_json::to_string(&StreamEvent { timestamp, outcome })
+}
+
+/// Write `outcome` to stdout as one JSON line, flushed at once so a
+/// consumer sees it as it happens. The line is written under the stdout
+/// lock, so lines from circuits checked concurrently never interleave.
+fn stream(outcome: &CheckOutcome) {
+    let written = event_line(outcome, Utc::now())
+        .map_err(std::io::Error::from)
+        .and_then(|line| {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()
+        });
+    if let Err(e) = written {
+        warn!(
+            circuit_id = outcome.circuit_id(),
+            error = %e,
+            "failed to stream check result"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use qtop_core::Circuit;
+
+    fn circuit(id: u64, interval: Option<f64>) -> Circuit {
+        let mut circuit = Circuit::new(id, vec![0.0, 2.0, 4.0]);
+        circuit.interval = interval;
+        circuit
+    }
+
+    async fn checks(state: &MonitorState, id: u64) -> usize {
+        let status = state.registry.status(id).await.unwrap();
+        status.latency.map_or(0, |latency| latency.samples)
+    }
+
+    /// Wait, up to a generous limit, for `id` to have been checked `n` times.
+    async fn until_checked(state: &MonitorState, id: u64, n: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while checks(state, id).await < n {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn circuits_are_checked_on_their_own_intervals() {
+        let state = MonitorState::new(
+            &[circuit(1, Some(0.01)), circuit(2, None)],
+            Metrics::new().unwrap(),
+        );
+        let settings = Settings {
+            interval: Duration::from_secs(60),
+            timeout: None,
+            stream: false,
+        };
+        let schedule = Arc::new(Schedule::new(settings, None));
+        let mut scheduler = Scheduler::new(state.clone(), schedule.clone());
+        scheduler.sync().await;
+
+        // The fast circuit keeps being checked while the slow one waits
+        // out its minute.
+        until_checked(&state, 1, 5).await;
+        until_checked(&state, 2, 1).await;
+        assert!(state.is_ready());
+        assert_eq!(checks(&state, 2).await, 1);
+
+        // A circuit added at runtime gets a schedule of its own.
+        state.registry.add(circuit(3, Some(60.0))).await;
+        scheduler.sync().await;
+        until_checked(&state, 3, 1).await;
+
+        scheduler.stop().await;
+        assert!(schedule.checked() >= 7);
+    }
+
+    #[test]
+    fn stream_events_are_single_json_lines() {
+        let outcome = CheckOutcome::Failed {
+            circuit_id: 4,
+            error: "phase data\nends early".to_string(),
+        };
+        let at = "2026-10-14T09:30:00Z".parse().unwrap();
+        let line = event_line(&outcome, at).unwrap();
+        assert!(!line.contains('\n'));
+        let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(event["timestamp"], "2026-10-14T09:30:00Z");
+        assert_eq!(event["circuit_id"], 4);
+        assert_eq!(event["error"], "phase data\nends early");
+    }
+}
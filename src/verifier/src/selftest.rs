@@ -0,0 +1,111 @@
+use crate::bench;
+use crate::ui::Glyph;
+use qtop_core::Circuit;
+use std::f64::consts::{PI, TAU};
+
+/// A circuit whose winding is known independently of the code under test.
+struct Reference {
+    name: &'static str,
+    phases: Vec<f64>,
+    expected: i64,
+}
+
+/// Seed of the jittered reference, fixed so every run checks the same loop.
+const JITTER_SEED: u64 = 0x5e1f_7e57;
+
+/// `samples` evenly spaced phases around a loop that winds `turns` times,
+/// starting from `offset`.
+fn evenly_spaced(samples: usize, turns: f64, offset: f64) -> Vec<f64> {
+    (0..samples)
+        .map(|i| offset + turns * TAU * i as f64 / samples as f64)
+        .collect()
+}
+
+fn references() -> Vec<Reference> {
+    vec![
+        Reference {
+            name: "trivial loop",
+            phases: vec![1.25; 8],
+            expected: 0,
+        },
+        Reference {
+            name: "single turn",
+            phases: evenly_spaced(8, 1.0, 0.0),
+            expected: 1,
+        },
+        Reference {
+            name: "single reverse turn",
+            phases: evenly_spaced(8, -1.0, 0.0),
+            expected: -1,
+        },
+        Reference {
+            name: "double turn",
+            phases: evenly_spaced(16, 2.0, 0.0),
+            expected: 2,
+        },
+        // Every step lands on the other side of ±π from where it started.
+        Reference {
+            name: "turn across the branch cut",
+            phases: evenly_spaced(8, 1.0, PI - 0.1),
+            expected: 1,
+        },
+        Reference {
+            name: "jittered triple turn",
+            phases: bench::synthetic_phases(64, &mut bench::rng(JITTER_SEED)),
+            expected: 3,
+        },
+    ]
+}
+
+/// Verify every reference circuit, printing a line for each. Returns
+/// whether all of them passed.
+pub fn run() -> bool {
+    let references = references();
+    let mut failed = 0;
+    for (id, reference) in (1..).zip(&references) {
+        let result = Circuit::new(id, reference.phases.clone())
+            .with_expected_winding(reference.expected)
+            .verify();
+        if result.valid {
+            println!(
+                "{} {}: winding {}",
+                Glyph::Ok,
+                reference.name,
+                result.winding_number
+            );
+        } else {
+            failed += 1;
+            println!(
+                "{} {}: winding {}, expected {}",
+                Glyph::Fail,
+                reference.name,
+                result.winding_number,
+                reference.expected
+            );
+        }
+    }
+    println!(
+        "{} {} passed, {} failed",
+        Glyph::Summary,
+        references.len() - failed,
+        failed
+    );
+    failed == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_reference_has_its_expected_winding() {
+        for reference in references() {
+            assert_eq!(
+                qtop_core::winding_number(&reference.phases).value,
+                reference.expected,
+                "{}",
+                reference.name
+            );
+        }
+    }
+}
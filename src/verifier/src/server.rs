@@ -0,0 +1,721 @@
+use crate::cache::{CacheStats, WindingCache};
+use crate::cli::DEFAULT_DRAIN_TIMEOUT;
+use crate::metrics::Metrics;
+use crate::registry::{CircuitRegistry, CircuitStatus, Drained};
+use axum::error_handling::HandleErrorLayer;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{BoxError, Json, Router};
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use futures::future::{BoxFuture, FutureExt};
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use qtop_core::Circuit;
+use serde::Serialize;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::ServiceBuilder;
+use tracing::{info, warn};
+
+/// Circuits under active monitoring and their verification status, shared
+/// between the monitoring loop and the HTTP server.
+#[derive(Clone)]
+pub struct MonitorState {
+    pub registry: CircuitRegistry,
+    /// Set once every circuit monitored from the start has been checked.
+    ready: Arc<AtomicBool>,
+    pub metrics: Arc<Metrics>,
+    /// Winding numbers of unchanged circuits, unless caching is disabled.
+    pub cache: Option<Arc<WindingCache>>,
+    /// How long a shutdown waits for the circuit's verifications to finish.
+    pub drain_timeout: Duration,
+}
+
+impl MonitorState {
+    pub fn new(circuits: &[Circuit], metrics: Metrics) -> Self {
+        Self {
+            registry: CircuitRegistry::new(circuits),
+            ready: Arc::new(AtomicBool::new(false)),
+            metrics: Arc::new(metrics),
+            cache: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Reuse winding numbers from `cache` for circuits whose phases have
+    /// not changed since they were last verified.
+    pub fn with_cache(mut self, cache: Arc<WindingCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Report ready on `/readyz` from now on.
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    circuits: Vec<CircuitStatus>,
+    /// Absent when caching is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<CacheStats>,
+}
+
+pub fn router(state: MonitorState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/status", get(status))
+        .route("/circuit", post(add_circuit))
+        .route("/circuit/{id}", get(circuit).delete(remove_circuit))
+        .route("/metrics", get(metrics))
+        .route("/shutdown/{id}", post(shutdown))
+        .with_state(state)
+}
+
+/// Bounds on the work the server takes on, applied to every endpoint alike
+/// so that slow or numerous clients cannot tie it up.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Longest a request may take to be answered; slower ones get a 408.
+    /// Also how long a connection may go without sending a complete set of
+    /// request headers, idle or not, before it is closed.
+    pub request_timeout: Duration,
+    /// Most connections open at once, over TLS or not. Connections beyond
+    /// that get a 503 and are closed rather than queued.
+    pub max_connections: usize,
+}
+
+/// `router` with the request timeout of `limits` applied in front of all of
+/// its routes.
+pub fn limited(router: Router, limits: Limits) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(reject))
+            .timeout(limits.request_timeout),
+    )
+}
+
+async fn reject(error: BoxError) -> Response {
+    let (status, message) = if error.is::<tower::timeout::error::Elapsed>() {
+        (StatusCode::REQUEST_TIMEOUT, "request timed out")
+    } else {
+        warn!(error = %error, "request failed");
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+    };
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Serve the monitoring endpoints on `listener` until `shutdown` resolves,
+/// over HTTPS when `tls` is given and plain HTTP otherwise.
+pub async fn serve(
+    listener: TcpListener,
+    state: MonitorState,
+    limits: Limits,
+    tls: Option<RustlsConfig>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    serve_app(listener, router(state), limits, tls, shutdown).await
+}
+
+/// Serve `app` like [`serve`] does the monitoring endpoints, with the same
+/// `limits` and optional TLS; also used for the gRPC interface.
+pub async fn serve_app(
+    listener: TcpListener,
+    app: Router,
+    limits: Limits,
+    tls: Option<RustlsConfig>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let app = limited(app, limits).into_make_service();
+    let handle = axum_server::Handle::new();
+    let stop = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        stop.graceful_shutdown(None);
+    });
+
+    let mut server = axum_server::from_tcp(listener.into_std()?).handle(handle);
+    server
+        .http_builder()
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(limits.request_timeout);
+    match tls {
+        Some(tls) => {
+            let acceptor = ConnectionLimit::new(RustlsAcceptor::new(tls), limits);
+            server.acceptor(acceptor).serve(app).await
+        }
+        None => {
+            let acceptor = ConnectionLimit::new(DefaultAcceptor::new(), limits);
+            server.acceptor(acceptor).serve(app).await
+        }
+    }
+}
+
+/// Acceptor admitting a connection to `inner` only while it can take one
+/// of `permits`, which the connection holds until it is closed, and only
+/// once it has sent something within `first_byte_timeout`. hyper's header
+/// timeout only starts after the first bytes, so without that a silent
+/// connection would hold its permit forever. Connections over the limit
+/// are answered with a 503 and closed.
+#[derive(Clone)]
+struct ConnectionLimit<A> {
+    inner: A,
+    permits: Arc<Semaphore>,
+    first_byte_timeout: Duration,
+}
+
+impl<A> ConnectionLimit<A> {
+    fn new(inner: A, limits: Limits) -> Self {
+        Self {
+            inner,
+            permits: Arc::new(Semaphore::new(limits.max_connections)),
+            first_byte_timeout: limits.request_timeout,
+        }
+    }
+}
+
+impl<A, S> Accept<TcpStream, S> for ConnectionLimit<A>
+where
+    A: Accept<Admitted, S> + Clone + Send + 'static,
+    A::Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    A::Service: Send,
+    A::Future: Send,
+    S: Send + 'static,
+{
+    type Stream = A::Stream;
+    type Service = A::Service;
+    type Future = BoxFuture<'static, io::Result<(A::Stream, A::Service)>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let inner = self.inner.clone();
+        let first_byte_timeout = self.first_byte_timeout;
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            warn!(
+                peer = ?stream.peer_addr().ok(),
+                "turning connection away: server is at its connection limit"
+            );
+            return async move {
+                let admitted = Admitted {
+                    stream,
+                    _permit: None,
+                };
+                let _ = tokio::time::timeout(first_byte_timeout, async move {
+                    let (stream, _) = inner.accept(admitted, service).await?;
+                    turn_away(stream).await
+                })
+                .await;
+                Err(io::Error::other("server is at its connection limit"))
+            }
+            .boxed();
+        };
+        async move {
+            tokio::time::timeout(first_byte_timeout, stream.readable())
+                .await
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::TimedOut, "connection sent nothing")
+                })??;
+            let admitted = Admitted {
+                stream,
+                _permit: Some(permit),
+            };
+            inner.accept(admitted, service).await
+        }
+        .boxed()
+    }
+}
+
+/// Answer every request on `stream` with a 503, closing it after the first
+/// over HTTP/1.
+async fn turn_away(stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static) -> io::Result<()> {
+    let overloaded = Router::new().fallback(|| async {
+        let body = serde_json::json!({ "error": "server is at its connection limit" });
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::CONNECTION, "close")],
+            Json(body),
+        )
+    });
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder.http1().keep_alive(false);
+    builder
+        .serve_connection(TokioIo::new(stream), TowerToHyperService::new(overloaded))
+        .await
+        .map_err(io::Error::other)
+}
+
+/// A connection, counted against the limit until it is dropped unless it
+/// is only being turned away.
+struct Admitted {
+    stream: TcpStream,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl AsyncRead for Admitted {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Admitted {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+// The probe endpoints never touch the circuit map, so they stay cheap no
+// matter how many circuits are monitored.
+
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readyz(State(state): State<MonitorState>) -> Response {
+    if state.is_ready() {
+        Json(serde_json::json!({ "status": "ready" })).into_response()
+    } else {
+        let body = serde_json::json!({ "status": "starting" });
+        (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+    }
+}
+
+async fn status(State(state): State<MonitorState>) -> Json<StatusResponse> {
+    let circuits = state.registry.statuses().await;
+    let cache = state.cache.as_ref().map(|cache| cache.stats());
+    Json(StatusResponse { circuits, cache })
+}
+
+async fn circuit(State(state): State<MonitorState>, Path(id): Path<u64>) -> Response {
+    match state.registry.status(id).await {
+        Some(status) => Json(status).into_response(),
+        None => not_found(id),
+    }
+}
+
+async fn add_circuit(State(state): State<MonitorState>, Json(circuit): Json<Circuit>) -> Response {
+    let id = circuit.id;
+    if let Err(e) = circuit.validate() {
+        let body = serde_json::json!({ "error": e.to_string() });
+        return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+    }
+    if !state.registry.add(circuit).await {
+        let body = serde_json::json!({ "error": format!("circuit {} is already monitored", id) });
+        return (StatusCode::CONFLICT, Json(body)).into_response();
+    }
+    info!(circuit_id = id, "circuit added to monitoring");
+    let body = serde_json::json!({ "circuit_id": id, "added": true });
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+async fn remove_circuit(State(state): State<MonitorState>, Path(id): Path<u64>) -> Response {
+    if !state.registry.remove(id).await {
+        return not_found(id);
+    }
+    info!(circuit_id = id, "circuit removed from monitoring");
+    Json(serde_json::json!({ "circuit_id": id, "removed": true })).into_response()
+}
+
+async fn metrics(State(state): State<MonitorState>) -> Response {
+    match state.metrics.encode() {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Drain the circuit, then stop monitoring it. `drained` in the response
+/// is false if verifications were still running at the drain timeout.
+async fn shutdown(State(state): State<MonitorState>, Path(id): Path<u64>) -> Response {
+    let Some(drained) = state.registry.drain(id, state.drain_timeout).await else {
+        return not_found(id);
+    };
+    let drained = log_shutdown(id, drained, state.drain_timeout);
+    Json(serde_json::json!({ "circuit_id": id, "shutdown": true, "drained": drained }))
+        .into_response()
+}
+
+/// Log how a shutdown's drain went. Returns whether it drained cleanly.
+pub fn log_shutdown(id: u64, drained: Drained, timeout: Duration) -> bool {
+    match drained {
+        Drained::Cleanly => {
+            warn!(
+                circuit_id = id,
+                "circuit removed from monitoring by emergency shutdown"
+            );
+            true
+        }
+        Drained::Forced => {
+            warn!(
+                circuit_id = id,
+                ?timeout,
+                "circuit force-removed by emergency shutdown; verification still running at the drain timeout"
+            );
+            false
+        }
+    }
+}
+
+fn not_found(id: u64) -> Response {
+    let body = serde_json::json!({ "error": format!("circuit {} not found", id) });
+    (StatusCode::NOT_FOUND, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use qtop_core::{Invariant, VerificationResult};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn get_json(state: &MonitorState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn status_lists_tracked_circuits_with_last_result() {
+        let state = MonitorState::new(
+            &[Circuit::new(1, vec![]), Circuit::new(2, vec![])],
+            Metrics::new().unwrap(),
+        );
+        state
+            .registry
+            .record(
+                VerificationResult {
+                    circuit_id: 2,
+                    invariant: Invariant::Winding,
+                    winding_number: 1,
+                    expected_winding: None,
+                    confidence: None,
+                    valid: true,
+                },
+                Duration::from_millis(4),
+            )
+            .await;
+
+        let (code, body) = get_json(&state, "/status").await;
+        assert_eq!(code, StatusCode::OK);
+        let circuits = body["circuits"].as_array().unwrap();
+        assert_eq!(circuits.len(), 2);
+        assert!(circuits[0]["last_result"].is_null());
+        assert!(circuits[0]["latency"].is_null());
+        assert_eq!(circuits[1]["last_result"]["winding_number"], 1);
+        assert_eq!(circuits[1]["latency"]["samples"], 1);
+        assert_eq!(circuits[1]["latency"]["p95_secs"], 0.004);
+        assert!(body.get("cache").is_none());
+
+        let cached = state.with_cache(Arc::default());
+        let (_, body) = get_json(&cached, "/status").await;
+        assert_eq!(body["cache"]["hits"], 0);
+    }
+
+    #[tokio::test]
+    async fn ready_only_after_first_pass() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap());
+
+        let (code, _) = get_json(&state, "/healthz").await;
+        assert_eq!(code, StatusCode::OK);
+        let (code, body) = get_json(&state, "/readyz").await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "starting");
+
+        state.mark_ready();
+        let (code, body) = get_json(&state, "/readyz").await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+    }
+
+    #[tokio::test]
+    async fn metrics_are_exported_in_text_format() {
+        let state = MonitorState::new(&[], Metrics::new().unwrap());
+        state.metrics.circuits_checked.inc_by(3);
+
+        let request = Request::get("/metrics").body(Body::empty()).unwrap();
+        let response = router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("qtop_circuits_checked_total 3"));
+        assert!(body.contains("qtop_winding_violations_total 0"));
+        assert!(body.contains("qtop_verification_duration_seconds_bucket"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_removes_circuit_from_monitoring() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap());
+        let shutdown = || {
+            let request = Request::post("/shutdown/1").body(Body::empty()).unwrap();
+            router(state.clone()).oneshot(request)
+        };
+
+        assert_eq!(shutdown().await.unwrap().status(), StatusCode::OK);
+        assert!(state.registry.circuits().await.is_empty());
+        assert_eq!(shutdown().await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_reports_a_forced_drain() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap())
+            .with_drain_timeout(Duration::from_secs(1));
+        let _verifying = state.registry.begin(1).await.unwrap();
+
+        let request = Request::post("/shutdown/1").body(Body::empty()).unwrap();
+        let response = router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["drained"], false);
+        assert!(state.registry.circuits().await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_requests_time_out() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap())
+            .with_drain_timeout(Duration::from_secs(60));
+        let _verifying = state.registry.begin(1).await.unwrap();
+        let limits = Limits {
+            request_timeout: Duration::from_secs(1),
+            max_connections: 8,
+        };
+
+        let request = Request::post("/shutdown/1").body(Body::empty()).unwrap();
+        let response = limited(router(state), limits)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    /// Serve `state` with `limits` on a fresh port until `stop` is sent.
+    async fn spawn_server(
+        state: MonitorState,
+        limits: Limits,
+    ) -> (
+        std::net::SocketAddr,
+        tokio::sync::oneshot::Sender<()>,
+        tokio::task::JoinHandle<io::Result<()>>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, state, limits, None, async {
+            let _ = stopped.await;
+        }));
+        (addr, stop, server)
+    }
+
+    /// Send a `/healthz` request on `stream` and return the response's
+    /// status line.
+    async fn healthz_on(stream: &mut TcpStream) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nhost: verifier\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = [0; 1024];
+        let read = stream.read(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response[..read]);
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    /// Whether the server closes `stream` within a few seconds.
+    async fn closed_by_server(stream: &mut TcpStream) -> bool {
+        use tokio::io::AsyncReadExt;
+        let mut buf = [0; 1024];
+        let closed = async {
+            loop {
+                match stream.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {}
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), closed)
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn connections_beyond_the_limit_are_closed() {
+        let state = MonitorState::new(&[], Metrics::new().unwrap());
+        let limits = Limits {
+            request_timeout: Duration::from_secs(30),
+            max_connections: 2,
+        };
+        let (addr, stop, server) = spawn_server(state, limits).await;
+
+        // Each held connection is served once, so it is known to be counted,
+        // then kept open and idle.
+        let mut held = Vec::new();
+        for _ in 0..2 {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            assert_eq!(healthz_on(&mut stream).await, "HTTP/1.1 200 OK");
+            held.push(stream);
+        }
+        let mut over_limit = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            healthz_on(&mut over_limit).await,
+            "HTTP/1.1 503 Service Unavailable"
+        );
+        assert!(closed_by_server(&mut over_limit).await);
+        assert_eq!(healthz_on(&mut held[0]).await, "HTTP/1.1 200 OK");
+
+        // Closing a held connection frees its slot once the server sees it.
+        drop(held.pop());
+        let mut status = String::new();
+        for _ in 0..100 {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            status = healthz_on(&mut stream).await;
+            if !status.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(status, "HTTP/1.1 200 OK");
+
+        drop(held);
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn connections_without_request_headers_are_closed() {
+        use tokio::io::AsyncWriteExt;
+        let state = MonitorState::new(&[], Metrics::new().unwrap());
+        let limits = Limits {
+            request_timeout: Duration::from_millis(200),
+            max_connections: 3,
+        };
+        let (addr, stop, server) = spawn_server(state, limits).await;
+
+        let mut silent = TcpStream::connect(addr).await.unwrap();
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        slow.write_all(b"GET /healthz HTTP/1.1\r\n").await.unwrap();
+        let mut kept_alive = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(healthz_on(&mut kept_alive).await, "HTTP/1.1 200 OK");
+        assert!(closed_by_server(&mut silent).await);
+        assert!(closed_by_server(&mut slow).await);
+        assert!(closed_by_server(&mut kept_alive).await);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_circuit_is_not_found() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap());
+
+        let (code, _) = get_json(&state, "/circuit/1").await;
+        assert_eq!(code, StatusCode::OK);
+
+        let (code, body) = get_json(&state, "/circuit/42").await;
+        assert_eq!(code, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "circuit 42 not found");
+    }
+
+    #[tokio::test]
+    async fn circuits_can_be_added_and_removed_at_runtime() {
+        let state = MonitorState::new(&[Circuit::new(1, vec![])], Metrics::new().unwrap());
+        let add = |body: &'static str| {
+            let request = Request::post("/circuit")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            router(state.clone()).oneshot(request)
+        };
+        let delete = |uri: &'static str| {
+            let request = Request::delete(uri).body(Body::empty()).unwrap();
+            router(state.clone()).oneshot(request)
+        };
+
+        let created = add(r#"{"id": 2, "phases": [3.14159, 3.14159]}"#);
+        assert_eq!(created.await.unwrap().status(), StatusCode::CREATED);
+        let duplicate = add(r#"{"id": 1, "phases": []}"#);
+        assert_eq!(duplicate.await.unwrap().status(), StatusCode::CONFLICT);
+        let malformed = add(r#"{"phases": []}"#);
+        assert!(malformed.await.unwrap().status().is_client_error());
+        for body in [
+            r#"{"id": 3, "phases": [0.0, 3.14], "interval": -1}"#,
+            r#"{"id": 3, "phases": [0.0, 3.14], "interval": 0}"#,
+        ] {
+            let response = add(body).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(body["error"].as_str().unwrap().contains("interval"));
+        }
+        let ids: Vec<u64> = state
+            .registry
+            .circuits()
+            .await
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, [1, 2]);
+
+        assert_eq!(delete("/circuit/1").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            delete("/circuit/1").await.unwrap().status(),
+            StatusCode::NOT_FOUND
+        );
+        let (_, body) = get_json(&state, "/status").await;
+        assert_eq!(body["circuits"][0]["circuit_id"], 2);
+    }
+}
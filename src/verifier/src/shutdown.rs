@@ -0,0 +1,237 @@
+use crate::cli::ShutdownArgs;
+use crate::retry::RetryPolicy;
+use crate::ui::Glyph;
+use anyhow::{bail, Context, Result};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::io::{BufRead, IsTerminal, Write};
+use tracing::warn;
+
+/// Refuse to go on with a shutdown unless it was explicitly confirmed:
+/// with `--force`, a `--confirm` token matching the configured
+/// `shutdown_token`, or by answering an interactive prompt.
+pub fn confirm(args: &ShutdownArgs) -> Result<()> {
+    if args.force {
+        warn!(
+            circuit_id = args.id,
+            "shutdown confirmation bypassed with --force"
+        );
+        return Ok(());
+    }
+    if let Some(token) = &args.confirm {
+        return check_token(token, args.shutdown_token.as_deref());
+    }
+    if std::io::stdin().is_terminal() && args.dry_run {
+        println!(
+            "{} {}: would ask for confirmation first",
+            Glyph::Info,
+            DRY_RUN
+        );
+        return Ok(());
+    }
+    if std::io::stdin().is_terminal() {
+        let stdin = std::io::stdin().lock();
+        if prompt(args.id, stdin, std::io::stderr())? {
+            return Ok(());
+        }
+        bail!("shutdown of circuit {} cancelled", args.id);
+    }
+    bail!(
+        "refusing to shut down circuit {} without confirmation; pass --confirm <TOKEN> or --force",
+        args.id
+    )
+}
+
+fn check_token(given: &str, configured: Option<&str>) -> Result<()> {
+    match configured {
+        None => bail!("--confirm needs a shutdown_token to be set in the config file"),
+        Some(expected) if given == expected => Ok(()),
+        Some(_) => bail!("confirmation token does not match the configured shutdown_token"),
+    }
+}
+
+/// Ask for a y/N answer; anything but `y` or `yes` declines.
+fn prompt(circuit_id: u64, mut input: impl BufRead, mut output: impl Write) -> Result<bool> {
+    write!(output, "Really shut down circuit {}? [y/N] ", circuit_id)?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// HTTP client for contacting the monitor, trusting `--ca-cert` on top of
+/// the built-in roots, or any certificate at all with `--insecure`.
+pub fn client(args: &ShutdownArgs) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(path) = &args.ca_cert {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("failed to read CA certificate {}", path.display()))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .with_context(|| format!("invalid CA certificate {}", path.display()))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if args.insecure {
+        warn!("TLS certificate verification disabled with --insecure");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("failed to build HTTP client")
+}
+
+/// Ask the verifier at `base_url` (scheme and `host:port`) to stop
+/// monitoring `circuit_id`. Only reports success once the server has
+/// acknowledged the request.
+pub async fn emergency_shutdown(
+    client: &reqwest::Client,
+    base_url: &str,
+    circuit_id: u64,
+    retry: RetryPolicy,
+) -> Result<()> {
+    warn!(circuit_id, base_url, "emergency shutdown triggered");
+
+    let url = format!("{}/shutdown/{}", base_url, circuit_id);
+    let response = retry
+        .send(|| client.post(&url))
+        .await
+        .with_context(|| format!("no verifier reachable on {}", base_url))?;
+
+    match response.status() {
+        status if status.is_success() => {}
+        StatusCode::NOT_FOUND => bail!(
+            "circuit {} is not being monitored by the verifier on {}",
+            circuit_id,
+            base_url
+        ),
+        status => bail!(
+            "verifier on {} rejected shutdown of circuit {}: {}",
+            base_url,
+            circuit_id,
+            status
+        ),
+    }
+
+    // Verifiers predating drains report no `drained` and removed at once.
+    let acknowledged: ShutdownResponse = response.json().await.unwrap_or_default();
+    if acknowledged.drained.unwrap_or(true) {
+        println!(
+            "{} Circuit {} has been safely shut down",
+            Glyph::Ok,
+            circuit_id
+        );
+    } else {
+        println!(
+            "{} Circuit {} was force-removed: a verification was still running at the drain timeout",
+            Glyph::Alert,
+            circuit_id
+        );
+    }
+    Ok(())
+}
+
+#[derive(Default, Deserialize)]
+struct ShutdownResponse {
+    drained: Option<bool>,
+}
+
+/// Prefix of every dry-run message, so none reads as a real shutdown.
+const DRY_RUN: &str = "DRY RUN — no action taken";
+
+/// Check that the shutdown in `args` is confirmed and that the verifier is
+/// monitoring its circuit, and so would accept it, without sending one.
+pub async fn dry_run(args: &ShutdownArgs, client: &reqwest::Client) -> Result<()> {
+    confirm(args).context(DRY_RUN)?;
+    look_up(client, &args.base_url(), args.id, args.retry.policy())
+        .await
+        .context(DRY_RUN)?;
+    println!(
+        "{} {}: would ask the verifier on {} to stop monitoring circuit {}",
+        Glyph::Info,
+        DRY_RUN,
+        args.base_url(),
+        args.id
+    );
+    Ok(())
+}
+
+/// Fail unless the verifier at `base_url` is monitoring `circuit_id`.
+async fn look_up(
+    client: &reqwest::Client,
+    base_url: &str,
+    circuit_id: u64,
+    retry: RetryPolicy,
+) -> Result<()> {
+    let url = format!("{}/circuit/{}", base_url, circuit_id);
+    let response = retry
+        .send(|| client.get(&url))
+        .await
+        .with_context(|| format!("no verifier reachable on {}", base_url))?;
+
+    match response.status() {
+        status if status.is_success() => {}
+        StatusCode::NOT_FOUND => bail!(
+            "circuit {} is not being monitored by the verifier on {}",
+            circuit_id,
+            base_url
+        ),
+        status => bail!(
+            "verifier on {} failed to look up circuit {}: {}",
+            base_url,
+            circuit_id,
+            status
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Command};
+    use clap::Parser;
+
+    #[test]
+    fn token_must_match_configured_secret() {
+        assert!(check_token("s3cret", Some("s3cret")).is_ok());
+        assert!(check_token("guess", Some("s3cret")).is_err());
+        assert!(check_token("s3cret", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn dry_run_failures_are_marked_as_dry_runs() {
+        // Nothing listens on a port just released by the OS.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let args = |extra: &[&str]| {
+            let port = port.to_string();
+            let mut argv = vec!["qtop-verifier", "shutdown", "7", "--dry-run", "-p", &port];
+            argv.extend(["--max-retries", "0"].iter().chain(extra));
+            match Cli::try_parse_from(argv).unwrap().command {
+                Command::Shutdown(args) => args,
+                _ => unreachable!(),
+            }
+        };
+        let client = reqwest::Client::new();
+
+        let unreachable = dry_run(&args(&["--force"]), &client).await.unwrap_err();
+        assert!(format!("{:#}", unreachable).starts_with("DRY RUN"));
+        let refused = dry_run(&args(&["--confirm", "guess"]), &client)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", refused).starts_with("DRY RUN"));
+    }
+
+    #[test]
+    fn prompt_defaults_to_no() {
+        let answer = |input: &str| prompt(1, input.as_bytes(), Vec::new()).unwrap();
+        assert!(answer("y\n"));
+        assert!(answer("YES\n"));
+        assert!(!answer("\n"));
+        assert!(!answer("n\n"));
+        assert!(!answer(""));
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Switch user-facing output to plain ASCII markers for every glyph.
+pub fn use_plain_markers(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+/// Whether ANSI colours should be used, per <https://no-color.org>.
+pub fn color_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Status markers prefixed to user-facing output lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyph {
+    Check,
+    Info,
+    Ok,
+    Fail,
+    Alert,
+    Summary,
+    History,
+    Stop,
+    Watch,
+}
+
+impl Glyph {
+    fn emoji(self) -> &'static str {
+        match self {
+            Glyph::Check => "🔍",
+            // The variation selector renders narrow in most terminals, so pad it.
+            Glyph::Info => "⚛️ ",
+            Glyph::Ok => "✅",
+            Glyph::Fail => "❌",
+            Glyph::Alert => "🚨",
+            Glyph::Summary => "📋",
+            Glyph::History => "🕒",
+            Glyph::Stop => "🛑",
+            Glyph::Watch => "👀",
+        }
+    }
+
+    fn ascii(self) -> &'static str {
+        match self {
+            Glyph::Check => "[CHECK]",
+            Glyph::Info => "[INFO]",
+            Glyph::Ok => "[OK]",
+            Glyph::Fail => "[FAIL]",
+            Glyph::Alert => "[ALERT]",
+            Glyph::Summary => "[SUMMARY]",
+            Glyph::History => "[HISTORY]",
+            Glyph::Stop => "[STOP]",
+            Glyph::Watch => "[WATCH]",
+        }
+    }
+}
+
+impl fmt::Display for Glyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if PLAIN.load(Ordering::Relaxed) {
+            f.write_str(self.ascii())
+        } else {
+            f.write_str(self.emoji())
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long a file must stay quiet before a change is reported. Editors
+/// often save with several writes, or a write followed by a rename.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Reports changes to a single file.
+pub struct FileWatcher {
+    // Dropping the watcher stops the notifications.
+    _watcher: RecommendedWatcher,
+    changes: mpsc::Receiver<()>,
+}
+
+impl FileWatcher {
+    /// Watch `path` for modification. The containing directory is watched
+    /// rather than the file itself so that editors which save by replacing
+    /// the file are still picked up.
+    pub fn new(path: &Path) -> Result<Self> {
+        let file_name: OsString = path
+            .file_name()
+            .ok_or_else(|| anyhow!("cannot watch {}: not a file", path.display()))?
+            .to_owned();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let (tx, changes) = mpsc::channel(1);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            let relevant = (event.kind.is_modify() || event.kind.is_create())
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(file_name.as_os_str()));
+            if relevant {
+                // A full channel already has a change pending.
+                let _ = tx.try_send(());
+            }
+        })
+        .context("failed to start file watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", path.display()))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait for the file to change and then settle.
+    pub async fn changed(&mut self) -> Result<()> {
+        debounce(&mut self.changes, DEBOUNCE).await
+    }
+}
+
+async fn debounce(changes: &mut mpsc::Receiver<()>, quiet: Duration) -> Result<()> {
+    let stopped = || anyhow!("file watcher stopped unexpectedly");
+    changes.recv().await.ok_or_else(stopped)?;
+    loop {
+        match tokio::time::timeout(quiet, changes.recv()).await {
+            Ok(Some(())) => continue,
+            Ok(None) => return Err(stopped()),
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{sleep, Instant};
+
+    #[tokio::test(start_paused = true)]
+    async fn rapid_changes_are_reported_once_settled() {
+        let (tx, mut rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            for _ in 0..3 {
+                tx.send(()).await.unwrap();
+                sleep(Duration::from_millis(100)).await;
+            }
+            // Keep the sender alive past the debounce window.
+            sleep(Duration::from_secs(60)).await;
+        });
+
+        let started = Instant::now();
+        debounce(&mut rx, DEBOUNCE).await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200) + DEBOUNCE);
+        assert!(elapsed < Duration::from_secs(1));
+    }
+}